@@ -0,0 +1,255 @@
+use std::io::{self, Stdout, Write};
+use std::time::Duration;
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use signal_hook::consts::{SIGINT, SIGTSTP};
+
+use super::{Attr, Backend, Cell, Grid, WideKey, A_BOLD, A_DIM, A_HIGHLIGHT, A_REVERSE, A_UNDERLINE, A_WIN};
+use super::{KEY_BACKSPACE, KEY_DC, KEY_DOWN, KEY_END, KEY_HOME, KEY_LEFT, KEY_NPAGE, KEY_PPAGE, KEY_RESIZE,
+    KEY_RIGHT, KEY_UP};
+
+/// The terminal through crossterm, in pure Rust, so cdls builds without libncurses.
+///
+/// The screen is drawn in a grid, and the rows which changed are written out on `refresh`.
+pub struct CrosstermBackend {
+    out: Stdout,
+    grid: Grid,
+    // what the terminal shows, None to draw every row again
+    drawn: Option<Grid>,
+    timeout: i32,
+}
+
+impl CrosstermBackend {
+    pub fn new() -> CrosstermBackend {
+        return CrosstermBackend {
+            out: io::stdout(),
+            grid: Grid::new(0, 0),
+            drawn: None,
+            timeout: -1,
+        };
+    }
+
+    /// Follow the size of the terminal, and draw everything again on the next refresh.
+    fn fit_grid(&mut self) {
+        match terminal::size() {
+            Ok((cols, rows)) => self.grid.resize(rows as i32, cols as i32),
+            Err(e) => log::warn!("Fail to read the terminal size. {}", e),
+        }
+        self.drawn = None;
+    }
+
+    fn write_attr(&mut self, attr: Attr) -> io::Result<()> {
+        queue!(self.out, SetAttribute(Attribute::Reset), ResetColor)?;
+        for (flag, attribute) in [(A_BOLD, Attribute::Bold), (A_DIM, Attribute::Dim),
+                (A_UNDERLINE, Attribute::Underlined), (A_REVERSE, Attribute::Reverse)] {
+            if attr & flag != 0 {
+                queue!(self.out, SetAttribute(attribute))?;
+            }
+        }
+        if attr & A_HIGHLIGHT != 0 {
+            queue!(self.out, SetForegroundColor(Color::Black), SetBackgroundColor(Color::White))?;
+        } else if attr & A_WIN != 0 {
+            queue!(self.out, SetForegroundColor(Color::Black), SetBackgroundColor(Color::Cyan))?;
+        }
+        return Ok(());
+    }
+
+    fn write_row(&mut self, y: usize, row: &[Cell], last_row: bool) -> io::Result<()> {
+        // blank cells at the end are cleared instead, and the bottom right cell is never
+        // written, so the terminal doesn't scroll
+        let mut end = row.iter().rposition(|cell| cell.text != " " || cell.attr != 0).map_or(0, |x| x + 1);
+        if last_row {
+            end = end.min(row.len().saturating_sub(1));
+        }
+
+        queue!(self.out, MoveTo(0, y as u16))?;
+        let mut attr = None;
+        for cell in &row[..end] {
+            if cell.text.is_empty() {
+                continue;
+            }
+            if attr != Some(cell.attr) {
+                self.write_attr(cell.attr)?;
+                attr = Some(cell.attr);
+            }
+            queue!(self.out, Print(&cell.text))?;
+        }
+        queue!(self.out, SetAttribute(Attribute::Reset), ResetColor, Clear(ClearType::UntilNewLine))?;
+        return Ok(());
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
+        let grid = self.grid.clone();
+        let rows = grid.rows();
+        for (y, row) in rows.iter().enumerate() {
+            let changed = match &self.drawn {
+                Some(drawn) => drawn.rows().get(y) != Some(row),
+                None => true,
+            };
+            if changed {
+                self.write_row(y, row, y + 1 == rows.len())?;
+            }
+        }
+        self.out.flush()?;
+        self.drawn = Some(grid);
+        return Ok(());
+    }
+
+    fn key(&mut self, key: KeyEvent) -> WideKey {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        return match key.code {
+            // raw mode reads these as keys, they stop or end cdls as the signals do
+            KeyCode::Char('c') if ctrl => raise(SIGINT),
+            KeyCode::Char('z') if ctrl => raise(SIGTSTP),
+            KeyCode::Char(c) if ctrl && c.is_ascii_alphabetic() => {
+                WideKey::Char((c.to_ascii_lowercase() as u8 & 0x1f) as char)
+            },
+            KeyCode::Char(c) => WideKey::Char(c),
+            KeyCode::Enter => WideKey::Char('\n'),
+            KeyCode::Tab => WideKey::Char('\t'),
+            KeyCode::Esc => WideKey::Char('\x1b'),
+            KeyCode::Backspace => WideKey::Code(KEY_BACKSPACE),
+            KeyCode::Delete => WideKey::Code(KEY_DC),
+            KeyCode::Up => WideKey::Code(KEY_UP),
+            KeyCode::Down => WideKey::Code(KEY_DOWN),
+            KeyCode::Left => WideKey::Code(KEY_LEFT),
+            KeyCode::Right => WideKey::Code(KEY_RIGHT),
+            KeyCode::Home => WideKey::Code(KEY_HOME),
+            KeyCode::End => WideKey::Code(KEY_END),
+            KeyCode::PageUp => WideKey::Code(KEY_PPAGE),
+            KeyCode::PageDown => WideKey::Code(KEY_NPAGE),
+            _ => WideKey::NoKey,
+        };
+    }
+
+    fn read_event(&mut self) -> io::Result<WideKey> {
+        if self.timeout >= 0 && !event::poll(Duration::from_millis(self.timeout as u64))? {
+            return Ok(WideKey::NoKey);
+        }
+        match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => Ok(self.key(key)),
+            Event::Resize(_, _) => {
+                self.fit_grid();
+                Ok(WideKey::Code(KEY_RESIZE))
+            },
+            _ => Ok(WideKey::NoKey),
+        }
+    }
+}
+
+impl Default for CrosstermBackend {
+    fn default() -> CrosstermBackend {
+        return CrosstermBackend::new();
+    }
+}
+
+/// Send `sig` to cdls, it is handled when the key read returns.
+fn raise(sig: i32) -> WideKey {
+    if let Err(e) = signal_hook::low_level::raise(sig) {
+        log::warn!("Fail to raise signal {}. {}", sig, e);
+    }
+    return WideKey::NoKey;
+}
+
+impl Backend for CrosstermBackend {
+    fn start(&mut self) {
+        if let Err(e) = terminal::enable_raw_mode() {
+            log::warn!("Fail to enable raw mode. {}", e);
+        }
+        if let Err(e) = execute!(self.out, EnterAlternateScreen, Hide) {
+            log::warn!("Fail to start the screen. {}", e);
+        }
+        self.fit_grid();
+    }
+
+    fn stop(&mut self) {
+        if let Err(e) = execute!(self.out, SetAttribute(Attribute::Reset), ResetColor, Show, LeaveAlternateScreen) {
+            log::warn!("Fail to stop the screen. {}", e);
+        }
+        if let Err(e) = terminal::disable_raw_mode() {
+            log::warn!("Fail to disable raw mode. {}", e);
+        }
+    }
+
+    fn suspend(&mut self) {
+        self.stop();
+    }
+
+    fn resume(&mut self) {
+        self.start();
+    }
+
+    fn size(&self) -> (i32, i32) {
+        return self.grid.size();
+    }
+
+    fn clear(&mut self) {
+        self.grid.clear();
+    }
+
+    fn move_to(&mut self, y: i32, x: i32) {
+        self.grid.move_to(y, x);
+    }
+
+    fn cursor_x(&self) -> i32 {
+        return self.grid.cursor_x();
+    }
+
+    fn add_str(&mut self, text: &str) {
+        self.grid.add_str(text);
+    }
+
+    fn clear_to_eol(&mut self) {
+        self.grid.clear_to_eol();
+    }
+
+    fn clear_to_bottom(&mut self) {
+        self.grid.clear_to_bottom();
+    }
+
+    fn attr_on(&mut self, attr: Attr) {
+        self.grid.attr_on(attr);
+    }
+
+    fn attr_off(&mut self, attr: Attr) {
+        self.grid.attr_off(attr);
+    }
+
+    fn change_attr(&mut self, y: i32, x: i32, n: i32, attr: Attr) {
+        self.grid.change_attr(y, x, n, attr);
+    }
+
+    fn refresh(&mut self) {
+        if let Err(e) = self.draw() {
+            log::warn!("Fail to draw the screen. {}", e);
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        return match &self.drawn {
+            Some(drawn) => drawn.lines(),
+            None => Vec::new(),
+        };
+    }
+
+    fn set_timeout(&mut self, ms: i32) {
+        self.timeout = ms;
+    }
+
+    fn read_key(&mut self) -> WideKey {
+        match self.read_event() {
+            Ok(key) => key,
+            Err(e) => {
+                // interrupted by a signal, handled by the caller
+                if e.kind() != io::ErrorKind::Interrupted {
+                    log::warn!("Fail to read a key. {}", e);
+                }
+                WideKey::NoKey
+            }
+        }
+    }
+}
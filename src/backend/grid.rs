@@ -0,0 +1,288 @@
+use unicode_width::UnicodeWidthChar;
+
+use super::Attr;
+
+/// A cell of the screen: a character with the combining marks following it. The cell right of a
+/// wide character has no text.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Cell {
+    pub text: String,
+    pub attr: Attr,
+}
+
+impl Cell {
+    fn blank() -> Cell {
+        return Cell { text: String::from(" "), attr: 0 };
+    }
+
+    fn is_continuation(&self) -> bool {
+        return self.text.is_empty();
+    }
+}
+
+/// The screen contents kept in memory, drawn as curses draws on its window: text wraps at the
+/// right edge and is dropped past the bottom, tabs go to the next multiple of 8.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Grid {
+    rows: i32,
+    cols: i32,
+    cells: Vec<Vec<Cell>>,
+    y: i32,
+    x: i32,
+    attr: Attr,
+}
+
+impl Grid {
+    pub fn new(rows: i32, cols: i32) -> Grid {
+        let rows = rows.max(0);
+        let cols = cols.max(0);
+        return Grid {
+            rows,
+            cols,
+            cells: vec![vec![Cell::blank(); cols as usize]; rows as usize],
+            y: 0,
+            x: 0,
+            attr: 0,
+        };
+    }
+
+    pub fn size(&self) -> (i32, i32) {
+        return (self.rows, self.cols);
+    }
+
+    /// Change the size, keeping the contents that still fit.
+    pub fn resize(&mut self, rows: i32, cols: i32) {
+        let mut resized = Grid::new(rows, cols);
+        for (y, row) in self.cells.iter().enumerate().take(resized.rows as usize) {
+            for (x, cell) in row.iter().enumerate().take(resized.cols as usize) {
+                resized.cells[y][x] = cell.clone();
+            }
+        }
+        resized.attr = self.attr;
+        *self = resized;
+    }
+
+    pub fn rows(&self) -> &[Vec<Cell>] {
+        return &self.cells;
+    }
+
+    pub fn cursor_x(&self) -> i32 {
+        return self.x;
+    }
+
+    pub fn clear(&mut self) {
+        for row in self.cells.iter_mut() {
+            row.fill(Cell::blank());
+        }
+        self.y = 0;
+        self.x = 0;
+    }
+
+    pub fn move_to(&mut self, y: i32, x: i32) {
+        if (0..self.rows).contains(&y) && (0..self.cols).contains(&x) {
+            self.y = y;
+            self.x = x;
+        }
+    }
+
+    pub fn attr_on(&mut self, attr: Attr) {
+        self.attr |= attr;
+    }
+
+    pub fn attr_off(&mut self, attr: Attr) {
+        self.attr &= !attr;
+    }
+
+    pub fn clear_to_eol(&mut self) {
+        if self.y < self.rows {
+            let y = self.y as usize;
+            for x in self.x.max(0) as usize..self.cols as usize {
+                self.cells[y][x] = Cell::blank();
+            }
+        }
+    }
+
+    pub fn clear_to_bottom(&mut self) {
+        self.clear_to_eol();
+        for row in self.cells.iter_mut().skip(self.y as usize + 1) {
+            row.fill(Cell::blank());
+        }
+    }
+
+    pub fn change_attr(&mut self, y: i32, x: i32, n: i32, attr: Attr) {
+        if !(0..self.rows).contains(&y) {
+            return;
+        }
+        let row = &mut self.cells[y as usize];
+        let end = if n < 0 { self.cols } else { (x + n).min(self.cols) };
+        for cell in row.iter_mut().take(end.max(0) as usize).skip(x.max(0) as usize) {
+            cell.attr = attr;
+        }
+    }
+
+    /// Go to the start of the next row, past the bottom if this is the last one.
+    fn new_line(&mut self) {
+        self.y += 1;
+        self.x = 0;
+    }
+
+    /// Put `text` of `width` cells at the cursor, wrapping first if it doesn't fit in the row.
+    fn put(&mut self, text: String, width: i32) {
+        if self.x + width > self.cols {
+            self.clear_to_eol();
+            self.new_line();
+        }
+        if self.y >= self.rows {
+            return;
+        }
+
+        let y = self.y as usize;
+        let x = self.x as usize;
+        let row = &mut self.cells[y];
+        // a wide character partly overwritten leaves a blank
+        if row[x].is_continuation() && x > 0 {
+            row[x - 1] = Cell::blank();
+        }
+        let end = x + width as usize;
+        if end < row.len() && row[end].is_continuation() {
+            row[end] = Cell::blank();
+        }
+
+        row[x] = Cell { text, attr: self.attr };
+        for cell in row.iter_mut().take(end).skip(x + 1) {
+            *cell = Cell { text: String::new(), attr: self.attr };
+        }
+        self.x += width;
+        if self.x >= self.cols {
+            self.new_line();
+        }
+    }
+
+    pub fn add_str(&mut self, text: &str) {
+        for c in text.chars() {
+            if self.y >= self.rows {
+                return;
+            }
+            match c {
+                '\n' => {
+                    self.clear_to_eol();
+                    self.new_line();
+                },
+                '\t' => {
+                    let spaces = 8 - self.x % 8;
+                    for _ in 0..spaces {
+                        self.put(String::from(" "), 1);
+                        if self.x == 0 {
+                            break;
+                        }
+                    }
+                },
+                c if c.is_control() => {
+                    // like unctrl, ^X
+                    self.put(String::from("^"), 1);
+                    self.put(char::from_u32(c as u32 ^ 0x40).unwrap_or('?').to_string(), 1);
+                },
+                c => match c.width() {
+                    Some(0) | None => self.combine(c),
+                    Some(width) => self.put(c.to_string(), width as i32),
+                },
+            }
+        }
+    }
+
+    /// Add a combining mark to the character before the cursor.
+    fn combine(&mut self, c: char) {
+        if self.y >= self.rows || self.x == 0 {
+            return;
+        }
+        let row = &mut self.cells[self.y as usize];
+        let mut x = self.x as usize - 1;
+        while x > 0 && row[x].is_continuation() {
+            x -= 1;
+        }
+        row[x].text.push(c);
+    }
+
+    /// The text of each row, without the blanks at its end.
+    pub fn lines(&self) -> Vec<String> {
+        return self.cells.iter()
+            .map(|row| row.iter().map(|cell| cell.text.as_str()).collect::<String>().trim_end().to_string())
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{A_BOLD, A_REVERSE};
+
+    #[test]
+    fn wraps_at_the_right_edge_and_drops_past_the_bottom() {
+        let mut grid = Grid::new(2, 4);
+        grid.add_str("abcdefghij");
+        assert_eq!(grid.lines(), ["abcd", "efgh"]);
+    }
+
+    #[test]
+    fn expands_tabs_and_newlines() {
+        let mut grid = Grid::new(3, 20);
+        grid.add_str("a\tb\nxyz");
+        grid.move_to(2, 0);
+        grid.add_str("\t\tc");
+        assert_eq!(grid.lines(), ["a       b", "xyz", "                c"]);
+    }
+
+    #[test]
+    fn wide_characters_take_two_cells() {
+        let mut grid = Grid::new(2, 5);
+        grid.add_str("报告x报");
+        assert_eq!(grid.lines(), ["报告x", "报"]);
+        assert_eq!(grid.cursor_x(), 2);
+
+        // overwriting half of a wide character blanks the other half
+        grid.move_to(0, 1);
+        grid.add_str("a");
+        assert_eq!(grid.lines()[0], " a告x");
+    }
+
+    #[test]
+    fn combining_marks_join_the_character_before() {
+        let mut grid = Grid::new(1, 5);
+        grid.add_str("e\u{301}x");
+        assert_eq!(grid.lines(), ["e\u{301}x"]);
+        assert_eq!(grid.cursor_x(), 2);
+    }
+
+    #[test]
+    fn keeps_attributes_of_cells() {
+        let mut grid = Grid::new(1, 6);
+        grid.add_str("a");
+        grid.attr_on(A_BOLD);
+        grid.add_str("b");
+        grid.attr_off(A_BOLD);
+        grid.add_str("c");
+        grid.change_attr(0, 2, 2, A_REVERSE);
+        let attrs: Vec<Attr> = grid.rows()[0].iter().map(|cell| cell.attr).collect();
+        assert_eq!(attrs, [0, A_BOLD, A_REVERSE, A_REVERSE, 0, 0]);
+    }
+
+    #[test]
+    fn clears_to_the_end_of_row_and_screen() {
+        let mut grid = Grid::new(3, 4);
+        grid.add_str("abcdefghijkl");
+        grid.move_to(1, 2);
+        grid.clear_to_eol();
+        assert_eq!(grid.lines(), ["abcd", "ef", "ijkl"]);
+        grid.move_to(0, 1);
+        grid.clear_to_bottom();
+        assert_eq!(grid.lines(), ["a", "", ""]);
+    }
+
+    #[test]
+    fn resize_keeps_what_fits() {
+        let mut grid = Grid::new(2, 4);
+        grid.add_str("abcdefgh");
+        grid.resize(3, 2);
+        assert_eq!(grid.lines(), ["ab", "ef", ""]);
+    }
+}
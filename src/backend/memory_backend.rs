@@ -0,0 +1,96 @@
+use std::collections::VecDeque;
+
+use super::{Attr, Backend, Grid, WideKey};
+
+/// A screen in memory with scripted keys, to run the screens without a terminal.
+pub struct MemoryBackend {
+    grid: Grid,
+    // the grid as it was last refreshed
+    screen: Grid,
+    keys: VecDeque<WideKey>,
+}
+
+impl MemoryBackend {
+    /// A screen of `rows` by `cols` cells, reading `keys` in order.
+    pub fn new(rows: i32, cols: i32, keys: Vec<WideKey>) -> MemoryBackend {
+        return MemoryBackend {
+            grid: Grid::new(rows, cols),
+            screen: Grid::new(rows, cols),
+            keys: VecDeque::from(keys),
+        };
+    }
+
+    /// The screen as it was last refreshed, with the attributes of each cell.
+    pub fn screen(&self) -> &Grid {
+        return &self.screen;
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn start(&mut self) {}
+
+    fn stop(&mut self) {}
+
+    fn suspend(&mut self) {}
+
+    fn resume(&mut self) {}
+
+    fn size(&self) -> (i32, i32) {
+        return self.grid.size();
+    }
+
+    fn clear(&mut self) {
+        self.grid.clear();
+    }
+
+    fn move_to(&mut self, y: i32, x: i32) {
+        self.grid.move_to(y, x);
+    }
+
+    fn cursor_x(&self) -> i32 {
+        return self.grid.cursor_x();
+    }
+
+    fn add_str(&mut self, text: &str) {
+        self.grid.add_str(text);
+    }
+
+    fn clear_to_eol(&mut self) {
+        self.grid.clear_to_eol();
+    }
+
+    fn clear_to_bottom(&mut self) {
+        self.grid.clear_to_bottom();
+    }
+
+    fn attr_on(&mut self, attr: Attr) {
+        self.grid.attr_on(attr);
+    }
+
+    fn attr_off(&mut self, attr: Attr) {
+        self.grid.attr_off(attr);
+    }
+
+    fn change_attr(&mut self, y: i32, x: i32, n: i32, attr: Attr) {
+        self.grid.change_attr(y, x, n, attr);
+    }
+
+    fn refresh(&mut self) {
+        self.screen = self.grid.clone();
+    }
+
+    fn lines(&self) -> Vec<String> {
+        return self.screen.lines();
+    }
+
+    fn screen_grid(&self) -> Option<Grid> {
+        return Some(self.screen.clone());
+    }
+
+    fn set_timeout(&mut self, _ms: i32) {}
+
+    /// The next scripted key, `NoKey` once they run out.
+    fn read_key(&mut self) -> WideKey {
+        return self.keys.pop_front().unwrap_or_default();
+    }
+}
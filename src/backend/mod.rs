@@ -0,0 +1,220 @@
+//! Drawing and input of the screens, on the terminal backend installed at startup.
+//!
+//! The screens call the free functions of this module, which go to the installed `Backend`.
+//! Key codes and attributes are the same for every backend.
+
+use std::cell::RefCell;
+
+mod grid;
+pub use grid::{Cell, Grid};
+
+mod memory_backend;
+pub use memory_backend::MemoryBackend;
+
+#[cfg(feature = "ncurses")]
+mod ncurses_backend;
+#[cfg(feature = "ncurses")]
+pub use ncurses_backend::NcursesBackend;
+
+#[cfg(feature = "crossterm")]
+mod crossterm_backend;
+#[cfg(feature = "crossterm")]
+pub use crossterm_backend::CrosstermBackend;
+
+// key codes of function keys, the values of ncurses
+pub const KEY_DOWN: i32 = 0x102;
+pub const KEY_UP: i32 = 0x103;
+pub const KEY_LEFT: i32 = 0x104;
+pub const KEY_RIGHT: i32 = 0x105;
+pub const KEY_HOME: i32 = 0x106;
+pub const KEY_BACKSPACE: i32 = 0x107;
+pub const KEY_DC: i32 = 0x14a;
+pub const KEY_NPAGE: i32 = 0x152;
+pub const KEY_PPAGE: i32 = 0x153;
+pub const KEY_ENTER: i32 = 0x157;
+pub const KEY_END: i32 = 0x168;
+pub const KEY_RESIZE: i32 = 0x19a;
+pub const ERR: i32 = -1;
+
+/// Drawing attributes, combined with `|`.
+pub type Attr = u32;
+pub const A_NORMAL: Attr = 0;
+pub const A_BOLD: Attr = 1;
+pub const A_DIM: Attr = 1 << 1;
+pub const A_UNDERLINE: Attr = 1 << 2;
+pub const A_REVERSE: Attr = 1 << 3;
+/// Black on white, the selected item and the bars.
+pub const A_HIGHLIGHT: Attr = 1 << 4;
+/// Black on cyan.
+pub const A_WIN: Attr = 1 << 5;
+
+/// A key read by `read_key`: a character, or a function key like `KEY_UP`.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub enum WideKey {
+    Char(char),
+    Code(i32),
+    #[default]
+    NoKey,
+}
+
+impl WideKey {
+    /// The key as `getch` returns it, for matching control characters and function keys.
+    /// Characters outside of it are -2, `ERR` if there is no key.
+    pub fn code(self) -> i32 {
+        match self {
+            WideKey::Char(c) if (c as u32) < 256 => c as i32,
+            WideKey::Char(_) => -2,
+            WideKey::Code(code) => code,
+            WideKey::NoKey => ERR,
+        }
+    }
+}
+
+/// A terminal the screens are drawn on, with the drawing model of curses: text is added at
+/// the cursor with the current attributes, and shows up on `refresh`.
+pub trait Backend {
+    /// Take the terminal over.
+    fn start(&mut self);
+    /// Give the terminal back, with echo and the cursor on.
+    fn stop(&mut self);
+    /// Give the terminal back while cdls is stopped by the shell.
+    fn suspend(&mut self);
+    /// Take the terminal over again after `suspend`, the screen is drawn again by the caller.
+    fn resume(&mut self);
+    /// Height and width of the screen.
+    fn size(&self) -> (i32, i32);
+    fn clear(&mut self);
+    fn move_to(&mut self, y: i32, x: i32);
+    fn cursor_x(&self) -> i32;
+    /// Add `text` at the cursor, wrapping at the right edge.
+    fn add_str(&mut self, text: &str);
+    fn clear_to_eol(&mut self);
+    fn clear_to_bottom(&mut self);
+    fn attr_on(&mut self, attr: Attr);
+    fn attr_off(&mut self, attr: Attr);
+    /// Set the attributes of `n` cells from row `y` and column `x`, keeping their text.
+    fn change_attr(&mut self, y: i32, x: i32, n: i32, attr: Attr);
+    fn refresh(&mut self);
+    /// The text of the screen as it was last refreshed, a string for each row.
+    fn lines(&self) -> Vec<String>;
+    /// The screen as it was last refreshed with the attributes of each cell, if it is kept in memory.
+    fn screen_grid(&self) -> Option<Grid> {
+        return None;
+    }
+    /// How long `read_key` waits, in milliseconds, or forever if negative.
+    fn set_timeout(&mut self, ms: i32);
+    /// Read a key, or `NoKey` when the timeout passes or a signal comes in.
+    fn read_key(&mut self) -> WideKey;
+}
+
+thread_local! {
+    static BACKEND: RefCell<Option<Box<dyn Backend>>> = const { RefCell::new(None) };
+}
+
+/// Make `backend` the one the screens of this thread are drawn on.
+pub fn install(backend: Box<dyn Backend>) {
+    BACKEND.with(|cell| *cell.borrow_mut() = Some(backend));
+}
+
+/// Take the installed backend back.
+pub fn uninstall() -> Option<Box<dyn Backend>> {
+    return BACKEND.with(|cell| cell.borrow_mut().take());
+}
+
+/// Call `f` on the installed backend, the default value if there is none.
+fn with<R: Default>(f: impl FnOnce(&mut dyn Backend) -> R) -> R {
+    return BACKEND.with(|cell| match cell.try_borrow_mut() {
+        Ok(mut backend) => match backend.as_mut() {
+            Some(backend) => f(backend.as_mut()),
+            None => R::default(),
+        },
+        // a panic while drawing, the panic hook can't restore the terminal
+        Err(_) => R::default(),
+    });
+}
+
+pub fn start() {
+    with(|b| b.start());
+}
+
+pub fn stop() {
+    with(|b| b.stop());
+}
+
+pub fn suspend() {
+    with(|b| b.suspend());
+}
+
+pub fn resume() {
+    with(|b| b.resume());
+}
+
+/// Screen height and width.
+pub fn size() -> (i32, i32) {
+    return with(|b| b.size());
+}
+
+pub fn clear() {
+    with(|b| b.clear());
+}
+
+pub fn mv(y: i32, x: i32) {
+    with(|b| b.move_to(y, x));
+}
+
+pub fn getcurx() -> i32 {
+    return with(|b| b.cursor_x());
+}
+
+pub fn addstr(text: &str) {
+    with(|b| b.add_str(text));
+}
+
+pub fn mvaddstr(y: i32, x: i32, text: &str) {
+    with(|b| {
+        b.move_to(y, x);
+        b.add_str(text);
+    });
+}
+
+pub fn clrtoeol() {
+    with(|b| b.clear_to_eol());
+}
+
+pub fn clrtobot() {
+    with(|b| b.clear_to_bottom());
+}
+
+pub fn attron(attr: Attr) {
+    with(|b| b.attr_on(attr));
+}
+
+pub fn attroff(attr: Attr) {
+    with(|b| b.attr_off(attr));
+}
+
+pub fn mvchgat(y: i32, x: i32, n: i32, attr: Attr) {
+    with(|b| b.change_attr(y, x, n, attr));
+}
+
+pub fn refresh() {
+    with(|b| b.refresh());
+}
+
+/// The text of the screen as it was last refreshed.
+pub fn lines() -> Vec<String> {
+    return with(|b| b.lines());
+}
+
+/// The cells of the screen as it was last refreshed, on a backend which keeps them.
+pub fn screen_grid() -> Option<Grid> {
+    return with(|b| b.screen_grid());
+}
+
+pub fn timeout(ms: i32) {
+    with(|b| b.set_timeout(ms));
+}
+
+pub fn read_key() -> WideKey {
+    return with(|b| b.read_key());
+}
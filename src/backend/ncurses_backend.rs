@@ -0,0 +1,173 @@
+use super::{Attr, Backend, Grid, WideKey, A_BOLD, A_DIM, A_HIGHLIGHT, A_REVERSE, A_UNDERLINE, A_WIN};
+
+static COLOR_PAIR_HIGHLIGHT: i16 = 1;
+static COLOR_PAIR_WIN: i16 = 2;
+
+/// The terminal through libncursesw.
+///
+/// What is drawn is kept in a grid too, as `innstr` of ncurses-rs can't read wide characters.
+pub struct NcursesBackend {
+    grid: Grid,
+    // the grid as it was last refreshed
+    screen: Grid,
+}
+
+impl NcursesBackend {
+    pub fn new() -> NcursesBackend {
+        return NcursesBackend {
+            grid: Grid::new(0, 0),
+            screen: Grid::new(0, 0),
+        };
+    }
+
+    /// Follow the size of the terminal, after a resize.
+    fn fit_grid(&mut self) {
+        let (rows, cols) = self.size();
+        if self.grid.size() != (rows, cols) {
+            self.grid.resize(rows, cols);
+        }
+    }
+}
+
+impl Default for NcursesBackend {
+    fn default() -> NcursesBackend {
+        return NcursesBackend::new();
+    }
+}
+
+/// The ncurses attributes without the color, and the color pair.
+fn curses_attr(attr: Attr) -> (ncurses::attr_t, i16) {
+    let mut curses_attr = 0;
+    for (flag, curses_flag) in [(A_BOLD, ncurses::A_BOLD()), (A_DIM, ncurses::A_DIM()),
+            (A_UNDERLINE, ncurses::A_UNDERLINE()), (A_REVERSE, ncurses::A_REVERSE())] {
+        if attr & flag != 0 {
+            curses_attr |= curses_flag;
+        }
+    }
+    let pair = if attr & A_HIGHLIGHT != 0 {
+        COLOR_PAIR_HIGHLIGHT
+    } else if attr & A_WIN != 0 {
+        COLOR_PAIR_WIN
+    } else {
+        0
+    };
+    return (curses_attr, pair);
+}
+
+impl Backend for NcursesBackend {
+    fn start(&mut self) {
+        // wide characters need the locale of the environment
+        ncurses::setlocale(ncurses::LcCategory::all, "");
+        ncurses::initscr();
+        ncurses::keypad(ncurses::stdscr(), true);
+        ncurses::noecho();
+        // esc is a key of its own, don't wait long for an escape sequence
+        ncurses::set_escdelay(25);
+        ncurses::curs_set(ncurses::CURSOR_VISIBILITY::CURSOR_INVISIBLE);
+
+        ncurses::start_color();
+        ncurses::init_pair(COLOR_PAIR_HIGHLIGHT, ncurses::COLOR_BLACK, ncurses::COLOR_WHITE);
+        ncurses::init_pair(COLOR_PAIR_WIN, ncurses::COLOR_BLACK, ncurses::COLOR_CYAN);
+        self.fit_grid();
+    }
+
+    fn stop(&mut self) {
+        ncurses::echo();
+        ncurses::keypad(ncurses::stdscr(), false);
+        ncurses::curs_set(ncurses::CURSOR_VISIBILITY::CURSOR_VISIBLE);
+        ncurses::endwin();
+    }
+
+    fn suspend(&mut self) {
+        ncurses::def_prog_mode();
+        ncurses::endwin();
+    }
+
+    fn resume(&mut self) {
+        ncurses::reset_prog_mode();
+        ncurses::refresh();
+        self.fit_grid();
+    }
+
+    fn size(&self) -> (i32, i32) {
+        return (ncurses::getmaxy(ncurses::stdscr()), ncurses::getmaxx(ncurses::stdscr()));
+    }
+
+    fn clear(&mut self) {
+        ncurses::clear();
+        self.fit_grid();
+        self.grid.clear();
+    }
+
+    fn move_to(&mut self, y: i32, x: i32) {
+        ncurses::mv(y, x);
+        self.grid.move_to(y, x);
+    }
+
+    fn cursor_x(&self) -> i32 {
+        return ncurses::getcurx(ncurses::stdscr());
+    }
+
+    fn add_str(&mut self, text: &str) {
+        ncurses::addstr(text);
+        self.grid.add_str(text);
+    }
+
+    fn clear_to_eol(&mut self) {
+        ncurses::clrtoeol();
+        self.grid.clear_to_eol();
+    }
+
+    fn clear_to_bottom(&mut self) {
+        ncurses::clrtobot();
+        self.grid.clear_to_bottom();
+    }
+
+    fn attr_on(&mut self, attr: Attr) {
+        let (curses_attr, pair) = curses_attr(attr);
+        ncurses::attron(curses_attr | ncurses::COLOR_PAIR(pair));
+        self.grid.attr_on(attr);
+    }
+
+    fn attr_off(&mut self, attr: Attr) {
+        let (curses_attr, pair) = curses_attr(attr);
+        ncurses::attroff(curses_attr | ncurses::COLOR_PAIR(pair));
+        self.grid.attr_off(attr);
+    }
+
+    fn change_attr(&mut self, y: i32, x: i32, n: i32, attr: Attr) {
+        let (curses_attr, pair) = curses_attr(attr);
+        ncurses::mvchgat(y, x, n, curses_attr, pair);
+        self.grid.change_attr(y, x, n, attr);
+    }
+
+    fn refresh(&mut self) {
+        ncurses::refresh();
+        self.screen = self.grid.clone();
+    }
+
+    fn lines(&self) -> Vec<String> {
+        return self.screen.lines();
+    }
+
+    fn set_timeout(&mut self, ms: i32) {
+        ncurses::timeout(ms);
+    }
+
+    fn read_key(&mut self) -> WideKey {
+        let key = match ncurses::get_wch() {
+            Some(ncurses::WchResult::Char(c)) => {
+                match char::from_u32(c) {
+                    Some(c) => WideKey::Char(c),
+                    None => WideKey::NoKey,
+                }
+            },
+            Some(ncurses::WchResult::KeyCode(code)) => WideKey::Code(code),
+            None => WideKey::NoKey,
+        };
+        if key == WideKey::Code(ncurses::KEY_RESIZE) {
+            self.fit_grid();
+        }
+        return key;
+    }
+}
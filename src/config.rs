@@ -0,0 +1,276 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use strum_macros::EnumIter;
+
+use crate::quote::QuotingStyle;
+use crate::search::{Matcher, SearchAlgo};
+use crate::truncate::Ellipsis;
+
+/// The settings of the columns, sorting, search and drawing, from the config file and
+/// changed while browsing.
+pub struct CdlsConfig {
+    pub item_type: bool,
+    pub permission: bool,
+    pub size: bool,
+    pub mtime: bool,
+    pub sortby: SortBy,
+    pub search_mode: bool,
+    pub search_editing: bool,
+    pub search_string: String,
+    pub search_cursor: usize,
+    pub search_enter_dir: bool,
+    pub search_filter: bool,
+    pub search_threshold: f32,
+    pub search_algo: SearchAlgo,
+    pub search_ignore_diacritics: bool,
+    pub search_pinyin: bool,
+    pub recursive_depth: usize,
+    pub recursive_max_results: usize,
+    pub recursive_ignore: Vec<String>,
+    pub scrolloff: usize,
+    pub center_cursor: bool,
+    pub name_ellipsis: Ellipsis,
+    pub quoting_style: QuotingStyle,
+}
+
+impl Default for CdlsConfig {
+    fn default() -> CdlsConfig {
+        return CdlsConfig {
+            item_type: true,
+            permission: true,
+            size: true,
+            mtime: true,
+            sortby: SortBy::Filename,
+            search_mode: false,
+            search_editing: false,
+            search_string: String::new(),
+            search_cursor: 0,
+            search_enter_dir: false,
+            search_filter: false,
+            search_threshold: 0.0,
+            search_algo: SearchAlgo::Trigram,
+            search_ignore_diacritics: false,
+            search_pinyin: false,
+            recursive_depth: 8,
+            recursive_max_results: 1000,
+            recursive_ignore: vec![String::from(".git"), String::from("node_modules"), String::from("target")],
+            scrolloff: 3,
+            center_cursor: false,
+            name_ellipsis: Ellipsis::Middle,
+            quoting_style: QuotingStyle::Escape,
+        };
+    }
+}
+
+#[derive(Debug, EnumIter, PartialEq, Eq, PartialOrd, Copy, Clone)]
+pub enum SortBy {
+    Filename,
+    ItemType,
+    Size,
+    MTime,
+}
+
+impl fmt::Display for SortBy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SortBy::Filename => write!(f, "File Name"),
+            SortBy::ItemType => write!(f, "Item Type"),
+            SortBy::Size => write!(f, "Size"),
+            SortBy::MTime => write!(f, "Modification Time"),
+        }
+    }
+}
+
+impl SortBy {
+    pub fn from_name(name: &str) -> Option<SortBy> {
+        match name {
+            "name" => Some(SortBy::Filename),
+            "type" => Some(SortBy::ItemType),
+            "size" => Some(SortBy::Size),
+            "mtime" => Some(SortBy::MTime),
+            _ => None,
+        }
+    }
+
+    pub fn to_usize(self) -> usize {
+        match self {
+            SortBy::Filename => 0,
+            SortBy::ItemType => 1,
+            SortBy::Size => 2,
+            SortBy::MTime => 3,
+        }
+    }
+}
+
+pub fn new_matcher(cdls_cfg: &CdlsConfig, query: &str) -> Matcher {
+    return Matcher::new(cdls_cfg.search_algo, query, cdls_cfg.search_ignore_diacritics, cdls_cfg.search_pinyin);
+}
+
+/// Leave search mode and stop applying the search string to the listing.
+pub fn clear_search(cdls_cfg: &mut CdlsConfig) {
+    cdls_cfg.search_mode = false;
+    cdls_cfg.search_editing = false;
+    cdls_cfg.search_filter = false;
+    cdls_cfg.search_string.clear();
+    cdls_cfg.search_cursor = 0;
+}
+
+fn config_file_path() -> Option<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    return Some(config_dir.join("cdls/config"));
+}
+
+fn parse_config_value<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+    return value.parse::<T>().map_err(|_| format!("invalid value of {}: {}", key, value));
+}
+
+pub fn apply_config_option(cdls_cfg: &mut CdlsConfig, key: &str, value: &str) -> Result<(), String> {
+    match key {
+        "columns" => {
+            let columns: Vec<&str> = value.split(',').map(str::trim).filter(|column| !column.is_empty()).collect();
+            if let Some(column) = columns.iter().find(|column| !["type", "permission", "size", "mtime"].contains(column)) {
+                return Err(format!("unknown column {}", column));
+            }
+            cdls_cfg.item_type = columns.contains(&"type");
+            cdls_cfg.permission = columns.contains(&"permission");
+            cdls_cfg.size = columns.contains(&"size");
+            cdls_cfg.mtime = columns.contains(&"mtime");
+        },
+        "sort_by" => {
+            cdls_cfg.sortby = match SortBy::from_name(value) {
+                Some(sortby) => sortby,
+                None => return Err(format!("invalid value of {}: {}", key, value)),
+            };
+        },
+        "search_accept" => {
+            cdls_cfg.search_enter_dir = match value {
+                "keep" => false,
+                "enter" => true,
+                _ => return Err(format!("invalid value of {}: {}", key, value)),
+            };
+        },
+        "search_pinyin" => cdls_cfg.search_pinyin = parse_config_value(key, value)?,
+        "search_ignore_diacritics" => cdls_cfg.search_ignore_diacritics = parse_config_value(key, value)?,
+        "search_threshold" => cdls_cfg.search_threshold = parse_config_value(key, value)?,
+        "search_algorithm" => {
+            cdls_cfg.search_algo = match SearchAlgo::from_name(value) {
+                Some(algo) => algo,
+                None => return Err(format!("unknown search algorithm {}", value)),
+            };
+        },
+        "recursive_depth" => cdls_cfg.recursive_depth = parse_config_value(key, value)?,
+        "recursive_max_results" => cdls_cfg.recursive_max_results = parse_config_value(key, value)?,
+        "recursive_ignore" => {
+            cdls_cfg.recursive_ignore = value.split(',')
+                .map(|pattern| pattern.trim().to_string())
+                .filter(|pattern| !pattern.is_empty())
+                .collect();
+        },
+        "scrolloff" => cdls_cfg.scrolloff = parse_config_value(key, value)?,
+        "center_cursor" => cdls_cfg.center_cursor = parse_config_value(key, value)?,
+        "name_ellipsis" => {
+            cdls_cfg.name_ellipsis = match Ellipsis::from_name(value) {
+                Some(style) => style,
+                None => return Err(format!("invalid value of {}: {}", key, value)),
+            };
+        },
+        "quoting_style" => {
+            cdls_cfg.quoting_style = match QuotingStyle::from_name(value) {
+                Some(style) => style,
+                None => return Err(format!("invalid value of {}: {}", key, value)),
+            };
+        },
+        _ => return Err(format!("unknown option {}", key)),
+    }
+    return Ok(());
+}
+
+/// Read `key = value` lines of the config file, '#' starts a comment line.
+pub fn load_config_file(cdls_cfg: &mut CdlsConfig) {
+    let path = match config_file_path() {
+        Some(path) => path,
+        None => return,
+    };
+
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Fail to read config file {}. {}", path.display(), e);
+            }
+            return;
+        }
+    };
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let rst = match line.split_once('=') {
+            Some((key, value)) => apply_config_option(cdls_cfg, key.trim(), value.trim()),
+            None => Err(format!("invalid line: {}", line)),
+        };
+        if let Err(e) = rst {
+            log::warn!("{}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_options() {
+        let mut cfg = CdlsConfig::default();
+        apply_config_option(&mut cfg, "search_accept", "enter").unwrap();
+        apply_config_option(&mut cfg, "search_algorithm", "regex").unwrap();
+        apply_config_option(&mut cfg, "scrolloff", "5").unwrap();
+        apply_config_option(&mut cfg, "recursive_ignore", " .git, ,build ").unwrap();
+        apply_config_option(&mut cfg, "quoting_style", "literal").unwrap();
+        apply_config_option(&mut cfg, "columns", "size, type").unwrap();
+        apply_config_option(&mut cfg, "sort_by", "mtime").unwrap();
+        assert!(cfg.search_enter_dir);
+        assert_eq!(cfg.search_algo, SearchAlgo::Regex);
+        assert_eq!(cfg.scrolloff, 5);
+        assert_eq!(cfg.recursive_ignore, [".git", "build"]);
+        assert_eq!(cfg.quoting_style, QuotingStyle::Literal);
+        assert!(cfg.item_type && cfg.size && !cfg.permission && !cfg.mtime);
+        assert_eq!(cfg.sortby, SortBy::MTime);
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        let mut cfg = CdlsConfig::default();
+        assert!(apply_config_option(&mut cfg, "scrolloff", "many").is_err());
+        assert!(apply_config_option(&mut cfg, "name_ellipsis", "start").is_err());
+        assert!(apply_config_option(&mut cfg, "colour", "red").is_err());
+        assert!(apply_config_option(&mut cfg, "columns", "type, owner").is_err());
+        assert!(cfg.item_type && cfg.permission);
+        assert_eq!(cfg.scrolloff, 3);
+    }
+
+    #[test]
+    fn clears_search() {
+        let mut cfg = CdlsConfig {
+            search_mode: true,
+            search_filter: true,
+            search_string: String::from("abc"),
+            search_cursor: 3,
+            ..CdlsConfig::default()
+        };
+        clear_search(&mut cfg);
+        assert!(!cfg.search_mode && !cfg.search_filter);
+        assert_eq!(cfg.search_string, "");
+        assert_eq!(cfg.search_cursor, 0);
+    }
+}
@@ -0,0 +1,49 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::quote::{self, QuotingStyle};
+
+/// An error of a file system operation the user asked for, shown in the status line.
+#[derive(Debug)]
+pub enum CdlsError {
+    /// The directory could not be read.
+    ListDir(PathBuf, io::Error),
+    /// Some entries of the directory could not be read, with the first error.
+    ListEntries(PathBuf, usize, io::Error),
+    /// The directory could not be made the current directory of the shell.
+    EnterDir(PathBuf, io::Error),
+    /// The current directory was deleted, with the ancestor gone up to.
+    Deleted(PathBuf, PathBuf),
+}
+
+/// The reason of an io error, worded for a directory.
+fn reason(err: &io::Error) -> String {
+    match err.kind() {
+        io::ErrorKind::PermissionDenied => String::from("permission denied"),
+        io::ErrorKind::NotFound => String::from("no such directory, it may have been deleted"),
+        io::ErrorKind::NotADirectory => String::from("not a directory"),
+        _ => err.to_string(),
+    }
+}
+
+impl fmt::Display for CdlsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CdlsError::ListDir(path, err) => {
+                write!(f, "Cannot list {}: {}", quote::quote_name(path.as_os_str(), QuotingStyle::Escape), reason(err))
+            },
+            CdlsError::ListEntries(path, count, err) => {
+                write!(f, "Cannot read {} entries of {}: {}", count,
+                    quote::quote_name(path.as_os_str(), QuotingStyle::Escape), err)
+            },
+            CdlsError::EnterDir(path, err) => {
+                write!(f, "Cannot enter {}: {}", quote::quote_name(path.as_os_str(), QuotingStyle::Escape), reason(err))
+            },
+            CdlsError::Deleted(path, parent) => {
+                write!(f, "{} no longer exists, went up to {}", quote::quote_name(path.as_os_str(), QuotingStyle::Escape),
+                    quote::quote_name(parent.as_os_str(), QuotingStyle::Escape))
+            },
+        }
+    }
+}
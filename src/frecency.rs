@@ -0,0 +1,396 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::PathBufExt;
+
+// same aging limit as z: once the total rank exceeds it, every rank decays
+static RANK_AGING_LIMIT: f64 = 9000.0;
+
+pub struct FrecencyEntry {
+    pub path: PathBuf,
+    pub rank: f64,
+    pub last_access: u64,
+}
+
+pub struct FrecencyDb {
+    db_path: PathBuf,
+    pub entries: Vec<FrecencyEntry>,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ImportKind {
+    Zoxide,
+    Autojump,
+    Z,
+}
+
+impl ImportKind {
+    pub fn from_name(name: &str) -> Option<ImportKind> {
+        match name {
+            "zoxide" => Some(ImportKind::Zoxide),
+            "autojump" => Some(ImportKind::Autojump),
+            "z" => Some(ImportKind::Z),
+            _ => None,
+        }
+    }
+
+    fn default_path(self) -> Option<PathBuf> {
+        let home = PathBuf::from(env::var_os("HOME")?);
+        match self {
+            // zoxide keeps a binary database, `import` reads its text form from `zoxide query`
+            ImportKind::Zoxide => None,
+            ImportKind::Autojump => {
+                let data_dir = match env::var_os("XDG_DATA_HOME") {
+                    Some(dir) => PathBuf::from(dir),
+                    None => home.join(".local/share"),
+                };
+                Some(data_dir.join("autojump/autojump.txt"))
+            },
+            ImportKind::Z => {
+                match env::var_os("_Z_DATA") {
+                    Some(path) => Some(PathBuf::from(path)),
+                    None => Some(home.join(".z")),
+                }
+            },
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs(),
+        Err(_) => 0,
+    }
+}
+
+impl FrecencyEntry {
+    /// Rank weighted by how recently the directory was visited, as z does.
+    pub fn frecency(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last_access);
+        if age < 3600 {
+            return self.rank * 4.0;
+        } else if age < 86400 {
+            return self.rank * 2.0;
+        } else if age < 604800 {
+            return self.rank / 2.0;
+        } else {
+            return self.rank / 4.0;
+        }
+    }
+}
+
+impl FrecencyDb {
+    pub fn default_path() -> Option<PathBuf> {
+        let data_dir = match env::var_os("XDG_DATA_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+        };
+        return Some(data_dir.join("cdls/frecency"));
+    }
+
+    /// Load the database, a missing file is an empty database.
+    pub fn load(db_path: &Path) -> FrecencyDb {
+        let mut db = FrecencyDb {
+            db_path: db_path.to_path_buf(),
+            entries: Vec::new(),
+        };
+
+        match fs::read_to_string(db_path) {
+            Ok(content) => {
+                db.entries = parse_z_lines(&content);
+            },
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    log::warn!("Fail to read frecency database {}. {}", db_path.display(), e);
+                }
+            }
+        }
+
+        return db;
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(parent) = self.db_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // write a temporary file first, so a crash never truncates the database
+        let tmp_path = self.db_path.with_extension("tmp");
+        let mut fd = fs::File::create(&tmp_path)?;
+        for entry in &self.entries {
+            // the z format has a line of UTF-8 per entry, other paths can't be stored
+            let path_str = match entry.path.to_str() {
+                Some(s) if !s.contains('\n') => s,
+                _ => {
+                    log::warn!("skip frecency entry not storable as a line of UTF-8: {}", entry.path.display());
+                    continue;
+                }
+            };
+            writeln!(fd, "{}|{}|{}", path_str, entry.rank, entry.last_access)?;
+        }
+        fd.sync_all()?;
+
+        return fs::rename(&tmp_path, &self.db_path);
+    }
+
+    /// Record a visit of `dir`.
+    pub fn add(&mut self, dir: &Path) {
+        let now = now_secs();
+        match self.entries.iter_mut().find(|e| e.path == dir) {
+            Some(entry) => {
+                entry.rank += 1.0;
+                entry.last_access = now;
+            },
+            None => {
+                self.entries.push(FrecencyEntry {
+                    path: dir.to_path_buf(),
+                    rank: 1.0,
+                    last_access: now,
+                });
+            }
+        }
+
+        self.age();
+    }
+
+    fn age(&mut self) {
+        let total: f64 = self.entries.iter().map(|e| e.rank).sum();
+        if total > RANK_AGING_LIMIT {
+            for entry in &mut self.entries {
+                entry.rank *= 0.99;
+            }
+            self.entries.retain(|e| e.rank >= 1.0);
+        }
+    }
+
+    /// Existing directories matching `query`, best match first.
+    ///
+    /// The fuzzy score of the directory name is weighted by the frecency of
+    /// the directory, an empty query ranks by frecency only. With `pinyin`, names with Han
+    /// characters also match by their pinyin spellings.
+    pub fn query(&self, query: &str, pinyin: bool) -> Vec<&FrecencyEntry> {
+        let now = now_secs();
+
+        let mut matches: Vec<(&FrecencyEntry, f64)> = self.entries.iter()
+            .filter(|e| e.path.is_dir())
+            .filter_map(|e| {
+                let weight = (1.0 + e.frecency(now)).ln();
+                if query.is_empty() {
+                    return Some((e, weight));
+                }
+                let score = e.path.fuzzy_search_score(query, pinyin) as f64;
+                if score > 0.0 {
+                    Some((e, score * weight))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        matches.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        return matches.into_iter().map(|(e, _)| e).collect();
+    }
+
+    /// Merge the database of another jump tool, return the number of imported directories.
+    pub fn import(&mut self, kind: ImportKind, file: Option<&Path>) -> io::Result<usize> {
+        let content = match file {
+            Some(path) => fs::read_to_string(path)?,
+            None if kind == ImportKind::Zoxide => {
+                let output = Command::new("zoxide").args(["query", "--list", "--score"]).output()?;
+                if !output.status.success() {
+                    return Err(io::Error::other("zoxide query failed"));
+                }
+                String::from_utf8_lossy(&output.stdout).to_string()
+            },
+            None => {
+                match kind.default_path() {
+                    Some(path) => fs::read_to_string(path)?,
+                    None => return Err(io::Error::other("HOME is not set, give the database FILE")),
+                }
+            }
+        };
+
+        let imported = match kind {
+            ImportKind::Zoxide => parse_score_path_lines(&content, ' '),
+            ImportKind::Autojump => parse_score_path_lines(&content, '\t'),
+            ImportKind::Z => parse_z_lines(&content),
+        };
+
+        let count = imported.len();
+        for entry in imported {
+            match self.entries.iter_mut().find(|e| e.path == entry.path) {
+                Some(existing) => {
+                    existing.rank += entry.rank;
+                    existing.last_access = existing.last_access.max(entry.last_access);
+                },
+                None => {
+                    self.entries.push(entry);
+                }
+            }
+        }
+        self.age();
+
+        return Ok(count);
+    }
+}
+
+/// `path|rank|time` lines, the format of both z and cdls.
+fn parse_z_lines(content: &str) -> Vec<FrecencyEntry> {
+    let mut entries = Vec::new();
+
+    for line in content.lines() {
+        // the path may contain '|', so split from the right
+        let mut fields = line.rsplitn(3, '|');
+        let time = fields.next().and_then(|s| s.trim().parse::<u64>().ok());
+        let rank = fields.next().and_then(|s| s.trim().parse::<f64>().ok());
+        let path = fields.next();
+        match (path, rank, time) {
+            (Some(path), Some(rank), Some(time)) if !path.is_empty() => {
+                entries.push(FrecencyEntry {
+                    path: PathBuf::from(path),
+                    rank,
+                    last_access: time,
+                });
+            },
+            _ => {
+                log::warn!("skip invalid frecency line: {}", line);
+            }
+        }
+    }
+
+    return entries;
+}
+
+/// `score<sep>path` lines, as written by autojump and `zoxide query --list --score`.
+fn parse_score_path_lines(content: &str, sep: char) -> Vec<FrecencyEntry> {
+    let now = now_secs();
+    let mut entries = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim_start();
+        let (score, path) = match line.split_once(sep) {
+            Some((score, path)) => (score.trim().parse::<f64>().ok(), path.trim_start()),
+            None => (None, ""),
+        };
+        match score {
+            Some(rank) if !path.is_empty() => {
+                entries.push(FrecencyEntry {
+                    path: PathBuf::from(path),
+                    rank,
+                    last_access: now,
+                });
+            },
+            _ => {
+                log::warn!("skip invalid import line: {}", line);
+            }
+        }
+    }
+
+    return entries;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(path: &Path, rank: f64, last_access: u64) -> FrecencyEntry {
+        return FrecencyEntry { path: path.to_path_buf(), rank, last_access };
+    }
+
+    fn db(entries: Vec<FrecencyEntry>) -> FrecencyDb {
+        return FrecencyDb { db_path: PathBuf::from("frecency"), entries };
+    }
+
+    #[test]
+    fn parses_z_lines() {
+        let entries = parse_z_lines("/home/a|b|12.5|1600000000\n/tmp|1|2\nbroken\n|1|2\n/x|rank|2\n");
+        let parsed: Vec<(&str, f64, u64)> = entries.iter()
+            .map(|e| (e.path.to_str().unwrap(), e.rank, e.last_access))
+            .collect();
+        assert_eq!(parsed, [("/home/a|b", 12.5, 1600000000), ("/tmp", 1.0, 2)]);
+    }
+
+    #[test]
+    fn parses_score_path_lines() {
+        let autojump = parse_score_path_lines("10.0\t/home/my dir\nno score\n", '\t');
+        assert_eq!(autojump.len(), 1);
+        assert_eq!(autojump[0].path, Path::new("/home/my dir"));
+        assert_eq!(autojump[0].rank, 10.0);
+
+        let zoxide = parse_score_path_lines("   4.5 /srv/data\n  12 /home\n", ' ');
+        let paths: Vec<&Path> = zoxide.iter().map(|e| e.path.as_path()).collect();
+        assert_eq!(paths, [Path::new("/srv/data"), Path::new("/home")]);
+    }
+
+    #[test]
+    fn ages_ranks_past_the_limit() {
+        let mut below = db(vec![entry(Path::new("/a"), 8000.0, 0), entry(Path::new("/b"), 1.0, 0)]);
+        below.age();
+        assert_eq!(below.entries.len(), 2);
+        assert_eq!(below.entries[0].rank, 8000.0);
+
+        let mut above = db(vec![entry(Path::new("/a"), 9000.0, 0), entry(Path::new("/b"), 1.0, 0)]);
+        above.age();
+        assert_eq!(above.entries.len(), 1);
+        assert_eq!(above.entries[0].rank, 9000.0 * 0.99);
+    }
+
+    #[test]
+    fn weights_rank_by_age() {
+        let e = entry(Path::new("/a"), 8.0, 1_000_000);
+        assert_eq!(e.frecency(1_000_000 + 60), 32.0);
+        assert_eq!(e.frecency(1_000_000 + 7200), 16.0);
+        assert_eq!(e.frecency(1_000_000 + 2 * 86400), 4.0);
+        assert_eq!(e.frecency(1_000_000 + 30 * 86400), 2.0);
+    }
+
+    #[test]
+    fn query_ranks_matches_by_frecency() {
+        let dir = TempDir::new().unwrap();
+        let alpha = dir.path().join("alpha");
+        let alpine = dir.path().join("alpine");
+        fs::create_dir(&alpha).unwrap();
+        fs::create_dir(&alpine).unwrap();
+        let now = now_secs();
+
+        let gone = dir.path().join("gone");
+        let db = db(vec![entry(&alpha, 2.0, now), entry(&alpine, 4.0, now), entry(&gone, 99.0, now)]);
+        let ranked: Vec<&Path> = db.query("alp", false).iter().map(|e| e.path.as_path()).collect();
+        assert_eq!(ranked, [alpine.as_path(), alpha.as_path()]);
+        // a better name match wins over a little more frecency
+        let ranked: Vec<&Path> = db.query("alpha", false).iter().map(|e| e.path.as_path()).collect();
+        assert_eq!(ranked.first(), Some(&alpha.as_path()));
+        // an empty query ranks by frecency, directories which are gone are left out
+        assert_eq!(db.query("", false).len(), 2);
+        assert!(db.query("zzzz", false).is_empty());
+    }
+
+    #[test]
+    fn query_matches_pinyin_if_enabled() {
+        let dir = TempDir::new().unwrap();
+        let report = dir.path().join("报告");
+        fs::create_dir(&report).unwrap();
+        let db = db(vec![entry(&report, 2.0, now_secs())]);
+        assert_eq!(db.query("bg", true).len(), 1);
+        assert!(db.query("bg", false).is_empty());
+    }
+
+    #[test]
+    fn imports_and_merges_a_file() {
+        let dir = TempDir::new().unwrap();
+        let z_file = dir.path().join("z");
+        fs::write(&z_file, "/a|3|100\n/b|1|50\n").unwrap();
+        let mut db = db(vec![entry(Path::new("/a"), 2.0, 200)]);
+        assert_eq!(db.import(ImportKind::Z, Some(&z_file)).unwrap(), 2);
+        assert_eq!(db.entries.len(), 2);
+        assert_eq!(db.entries[0].rank, 5.0);
+        assert_eq!(db.entries[0].last_access, 200);
+    }
+}
@@ -0,0 +1,19 @@
+#![allow(clippy::needless_return)]
+//! The directory browser of cdls without a screen, to embed it in other tools.
+//!
+//! A `Navigator` takes the key actions of the main screen and lists the directory they
+//! lead to as a `View`, which a front end draws.
+
+pub mod backend;
+pub mod config;
+pub mod error;
+pub mod frecency;
+pub mod listing;
+pub mod nav;
+pub mod quote;
+pub mod recursive;
+pub mod search;
+pub mod truncate;
+
+pub use config::{CdlsConfig, SortBy};
+pub use nav::{Action, CdlsCurPosition, ItemView, Navigator, PathBufExt, View};
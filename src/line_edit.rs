@@ -0,0 +1,78 @@
+use cdls::backend::{self, WideKey};
+
+fn byte_index(line: &str, char_idx: usize) -> usize {
+    match line.char_indices().nth(char_idx) {
+        Some((idx, _)) => idx,
+        None => line.len(),
+    }
+}
+
+/// Apply a readline style editing key to `line`, where `cursor` is a char index.
+///
+/// Return false if `key` is not an editing key.
+pub fn edit_line(line: &mut String, cursor: &mut usize, key: WideKey) -> bool {
+    let len = line.chars().count();
+    if *cursor > len {
+        *cursor = len;
+    }
+
+    if let WideKey::Char(c) = key {
+        if !c.is_control() {
+            line.insert(byte_index(line, *cursor), c);
+            *cursor += 1;
+            return true;
+        }
+    }
+
+    match key.code() {
+        backend::KEY_LEFT | 2 /* ctrl-b */ => {
+            *cursor = cursor.saturating_sub(1);
+        },
+        backend::KEY_RIGHT | 6 /* ctrl-f */ => {
+            if *cursor < len {
+                *cursor += 1;
+            }
+        },
+        backend::KEY_HOME | 1 /* ctrl-a */ => {
+            *cursor = 0;
+        },
+        backend::KEY_END | 5 /* ctrl-e */ => {
+            *cursor = len;
+        },
+        backend::KEY_BACKSPACE | 8 | 127 => {
+            if *cursor > 0 {
+                *cursor -= 1;
+                line.remove(byte_index(line, *cursor));
+            }
+        },
+        backend::KEY_DC => { // delete
+            if *cursor < len {
+                line.remove(byte_index(line, *cursor));
+            }
+        },
+        23 => { // ctrl-w, delete the word before cursor
+            let chars: Vec<char> = line.chars().collect();
+            let mut start = *cursor;
+            while start > 0 && chars[start - 1].is_whitespace() {
+                start -= 1;
+            }
+            while start > 0 && !chars[start - 1].is_whitespace() {
+                start -= 1;
+            }
+            line.replace_range(byte_index(line, start)..byte_index(line, *cursor), "");
+            *cursor = start;
+        },
+        21 => { // ctrl-u, delete to the line start
+            line.replace_range(..byte_index(line, *cursor), "");
+            *cursor = 0;
+        },
+        11 => { // ctrl-k, delete to the line end
+            line.truncate(byte_index(line, *cursor));
+        },
+        _ => {
+            return false;
+        }
+    }
+
+    return true;
+}
@@ -0,0 +1,255 @@
+//! The listing of a directory printed without a screen, for `cdls --list` and pipelines.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::config::CdlsConfig;
+use crate::error::CdlsError;
+use crate::nav::{get_current_dir_element, CdlsCurPosition, ItemView};
+
+/// How the listing is printed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ListFormat {
+    /// A row per item with the columns enabled in the config, like `ls -l`.
+    Text,
+    /// An array of objects with every column.
+    Json,
+    /// A header row, then a row per item with every column.
+    Csv,
+    /// A JSON object per line.
+    Ndjson,
+}
+
+impl ListFormat {
+    pub fn from_name(name: &str) -> Option<ListFormat> {
+        match name {
+            "text" => Some(ListFormat::Text),
+            "json" => Some(ListFormat::Json),
+            "csv" => Some(ListFormat::Csv),
+            "ndjson" => Some(ListFormat::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+/// List `dir` sorted as in the config, as the main screen lists it.
+///
+/// Return the items, and the error of the entries which couldn't be read if any.
+pub fn list_dir(dir: &Path, cdls_cfg: &CdlsConfig) -> Result<(Vec<ItemView>, Option<String>), CdlsError> {
+    let mut position = CdlsCurPosition::new(dir.to_path_buf());
+    let (dir_children, _) = get_current_dir_element(&mut position, cdls_cfg)?;
+    let items = dir_children.into_iter().map(|path| ItemView::new(path, cdls_cfg.quoting_style)).collect();
+    return Ok((items, position.status));
+}
+
+pub fn write_listing(out: &mut dyn Write, items: &[ItemView], cdls_cfg: &CdlsConfig, format: ListFormat)
+        -> io::Result<()> {
+    match format {
+        ListFormat::Text => write_text(out, items, cdls_cfg),
+        ListFormat::Json => {
+            writeln!(out, "[")?;
+            for (i, item) in items.iter().enumerate() {
+                let comma = if i + 1 < items.len() { "," } else { "" };
+                writeln!(out, "  {}{}", json_object(item), comma)?;
+            }
+            writeln!(out, "]")
+        },
+        ListFormat::Csv => {
+            writeln!(out, "name,path,type,permission,size,mtime")?;
+            for item in items {
+                let fields = [raw_name(item), item.path.to_string_lossy().to_string(), item.file_type.clone(),
+                    known(&item.permission), known(&item.size), rfc3339_mtime(item).unwrap_or_default()];
+                let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+                writeln!(out, "{}", fields.join(","))?;
+            }
+            Ok(())
+        },
+        ListFormat::Ndjson => {
+            for item in items {
+                writeln!(out, "{}", json_object(item))?;
+            }
+            Ok(())
+        },
+    }
+}
+
+/// The enabled columns in the order of the main screen, the size aligned right.
+fn write_text(out: &mut dyn Write, items: &[ItemView], cdls_cfg: &CdlsConfig) -> io::Result<()> {
+    let type_width = items.iter().map(|item| item.file_type.len()).max().unwrap_or_default();
+    let size_width = items.iter().map(|item| item.size.len()).max().unwrap_or_default();
+    for item in items {
+        let mut row = String::new();
+        if cdls_cfg.item_type {
+            row.push_str(&format!("{:<1$}  ", item.file_type, type_width));
+        }
+        if cdls_cfg.permission {
+            row.push_str(&format!("{}  ", item.permission));
+        }
+        if cdls_cfg.size {
+            row.push_str(&format!("{:>1$}  ", item.size, size_width));
+        }
+        if cdls_cfg.mtime {
+            row.push_str(&format!("{}  ", item.mtime));
+        }
+        row.push_str(&item.name);
+        writeln!(out, "{}", row)?;
+    }
+    return Ok(());
+}
+
+/// The file name as it is, for formats which escape it themselves.
+///
+/// These formats are UTF-8: bytes of a name which are not UTF-8 are replaced with U+FFFD, like
+/// the path, so such a name can't be told apart. The text format quotes it instead.
+fn raw_name(item: &ItemView) -> String {
+    return match item.path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => String::new(),
+    };
+}
+
+/// The modification time in UTC, like `2020-09-13T12:26:40Z`, so it reads the same in every time zone.
+fn rfc3339_mtime(item: &ItemView) -> Option<String> {
+    return item.modified.map(|time| DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Secs, true));
+}
+
+/// A column which couldn't be read is empty.
+fn known(column: &str) -> String {
+    return if column == "UNKNOWN" { String::new() } else { String::from(column) };
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    return quoted;
+}
+
+/// A string column, null if it couldn't be read.
+fn json_column(column: &str) -> String {
+    return if column == "UNKNOWN" { String::from("null") } else { json_string(column) };
+}
+
+fn json_object(item: &ItemView) -> String {
+    let size = match item.size.parse::<u64>() {
+        Ok(size) => size.to_string(),
+        Err(_) => String::from("null"),
+    };
+    let mtime = match rfc3339_mtime(item) {
+        Some(mtime) => json_string(&mtime),
+        None => String::from("null"),
+    };
+    return format!("{{\"name\":{},\"path\":{},\"type\":{},\"permission\":{},\"size\":{},\"mtime\":{}}}",
+        json_string(&raw_name(item)), json_string(&item.path.to_string_lossy()), json_string(&item.file_type),
+        json_column(&item.permission), size, mtime);
+}
+
+/// Quoted if it holds a separator, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        return format!("\"{}\"", field.replace('"', "\"\""));
+    }
+    return String::from(field);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::{Duration, UNIX_EPOCH};
+    use tempfile::TempDir;
+
+    fn item(name: &str, file_type: &str, size: &str) -> ItemView {
+        return ItemView {
+            path: PathBuf::from("dir").join(name),
+            name: String::from(name),
+            is_dir: file_type == "DIR",
+            file_type: String::from(file_type),
+            permission: String::from("rw-r--r--"),
+            size: String::from(size),
+            mtime: String::from("2020-09-13 12:26:40"),
+            modified: Some(UNIX_EPOCH + Duration::from_secs(1_600_000_000)),
+        };
+    }
+
+    fn listing(items: &[ItemView], cdls_cfg: &CdlsConfig, format: ListFormat) -> String {
+        let mut out = Vec::new();
+        write_listing(&mut out, items, cdls_cfg, format).unwrap();
+        return String::from_utf8(out).unwrap();
+    }
+
+    #[test]
+    fn text_shows_the_enabled_columns() {
+        let items = [item("a.txt", "FILE", "12"), item("docs", "DIR", "4096")];
+        let cdls_cfg = CdlsConfig { permission: false, mtime: false, ..CdlsConfig::default() };
+        assert_eq!(listing(&items, &cdls_cfg, ListFormat::Text), "FILE    12  a.txt\nDIR   4096  docs\n");
+
+        let names_only = CdlsConfig { item_type: false, permission: false, size: false, mtime: false,
+            ..CdlsConfig::default() };
+        assert_eq!(listing(&items, &names_only, ListFormat::Text), "a.txt\ndocs\n");
+    }
+
+    #[test]
+    fn json_escapes_names_and_types_columns() {
+        let mut unknown = item("new\n\"line\"", "NO-PERMISSION", "UNKNOWN");
+        unknown.permission = String::from("UNKNOWN");
+        unknown.modified = None;
+        let items = [item("a.txt", "FILE", "12"), unknown];
+        let cdls_cfg = CdlsConfig::default();
+
+        let first = "{\"name\":\"a.txt\",\"path\":\"dir/a.txt\",\"type\":\"FILE\",\"permission\":\"rw-r--r--\",\
+            \"size\":12,\"mtime\":\"2020-09-13T12:26:40Z\"}";
+        let second = "{\"name\":\"new\\n\\\"line\\\"\",\"path\":\"dir/new\\n\\\"line\\\"\",\"type\":\"NO-PERMISSION\",\
+            \"permission\":null,\"size\":null,\"mtime\":null}";
+        assert_eq!(listing(&items, &cdls_cfg, ListFormat::Ndjson), format!("{}\n{}\n", first, second));
+        assert_eq!(listing(&items, &cdls_cfg, ListFormat::Json), format!("[\n  {},\n  {}\n]\n", first, second));
+        assert_eq!(listing(&[], &cdls_cfg, ListFormat::Json), "[\n]\n");
+    }
+
+    #[test]
+    fn csv_quotes_fields() {
+        let items = [item("a,b", "FILE", "12"), item("say \"hi\"", "FILE", "UNKNOWN")];
+        assert_eq!(listing(&items, &CdlsConfig::default(), ListFormat::Csv),
+            "name,path,type,permission,size,mtime\n\
+            \"a,b\",\"dir/a,b\",FILE,rw-r--r--,12,2020-09-13T12:26:40Z\n\
+            \"say \"\"hi\"\"\",\"dir/say \"\"hi\"\"\",FILE,rw-r--r--,,2020-09-13T12:26:40Z\n");
+    }
+
+    #[test]
+    fn names_which_are_not_utf8_are_replaced() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut cafe = item("x", "FILE", "12");
+        cafe.path = PathBuf::from("dir").join(OsStr::from_bytes(b"caf\xe9"));
+        let ndjson = listing(&[cafe], &CdlsConfig::default(), ListFormat::Ndjson);
+        assert!(ndjson.starts_with("{\"name\":\"caf\u{fffd}\",\"path\":\"dir/caf\u{fffd}\""));
+    }
+
+    #[test]
+    fn lists_in_the_sort_of_the_config() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a"), "long content").unwrap();
+        fs::write(dir.path().join("b"), "").unwrap();
+        let cdls_cfg = CdlsConfig { sortby: crate::SortBy::Size, ..CdlsConfig::default() };
+        let (items, status) = list_dir(dir.path(), &cdls_cfg).unwrap();
+        let names: Vec<&str> = items.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(names, ["b", "a"]);
+        assert_eq!(status, None);
+
+        assert!(list_dir(&dir.path().join("missing"), &cdls_cfg).is_err());
+    }
+}
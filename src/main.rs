@@ -1,4 +1,5 @@
-extern crate ncurses;
+#![allow(clippy::needless_return)]
+
 extern crate simplelog;
 extern crate log;
 extern crate chrono;
@@ -7,34 +8,93 @@ extern crate strum_macros;
 
 use std::env;
 use std::fs;
-use std::os::unix::fs::FileTypeExt;
-use std::os::unix::fs::PermissionsExt;
+use std::io::{self, IsTerminal, Write};
 //use nix::sys::signal;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use simplelog::*;
 use std::process::{Command, exit};
 use std::os::unix::process::CommandExt;
 use std::env::set_current_dir;
 
-use chrono::offset::{Utc, Local};
-use chrono::{DateTime, NaiveDateTime};
 use strum::IntoEnumIterator;
-use strum_macros::EnumIter;
-use std::fmt;
-use rust_fuzzy_search::fuzzy_compare;
-use encoding8::ascii::is_printable;
-
-static COLOR_PAIR_HIGHLIGHT: i16 = 1;
-static COLOR_PAIR_WIN: i16 = 2;
-static HELP_STR: &str = "Usage: cdls [OPTION]\n
+use std::thread;
+use std::time::{Duration, Instant};
+mod line_edit;
+mod terminal;
+mod script;
+#[cfg(test)]
+mod screen_tests;
+use cdls::backend::{self, WideKey};
+use cdls::config::{self, clear_search, new_matcher, CdlsConfig, SortBy};
+use cdls::error::CdlsError;
+use cdls::frecency::{self, FrecencyDb, ImportKind};
+use cdls::listing::{self, ListFormat};
+use cdls::nav::{get_file_metadata_element, list_current_dir, Action, CdlsCurPosition, Navigator, PathBufExt};
+use cdls::quote::{self, QuotingStyle};
+use cdls::recursive::{RecursiveOptions, RecursiveSearch};
+use cdls::search::Matcher;
+use cdls::truncate::{self, Ellipsis};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// keys of the main screen actions, other printable keys start a type-ahead jump
+static ACTION_KEYS: &[char] = &['q', 'c', 'f', '/', 'F', 'h', 's', 'z', 'j', 'k', 'g', 'G', ':',
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
+// a larger count prefix is clamped to the list anyway
+static MAX_COUNT: usize = 1_000_000;
+static TYPE_AHEAD_TIMEOUT: Duration = Duration::from_millis(1000);
+// how often signals are checked while waiting for a key
+static SIGNAL_POLL_MS: i32 = 100;
+// below it only the list is drawn, without header and footer
+static BARS_MIN_HEIGHT: i32 = 6;
+// below them a "terminal too small" message is drawn instead
+static MIN_HEIGHT: i32 = 3;
+static MIN_WIDTH: i32 = 20;
+// optional columns are dropped until the item name has this many cells
+static NAME_MIN_WIDTH: i32 = 16;
+// widths in cells of the cursor marker and the columns before the name
+static MARKER_WIDTH: i32 = 8;
+static TYPE_WIDTH: i32 = 8;
+static PERMISSION_WIDTH: i32 = 16;
+static SIZE_WIDTH: i32 = 16;
+static MTIME_WIDTH: i32 = 24;
+static RANK_WIDTH: i32 = 8;
+// screen size of a replay, without --size
+static REPLAY_ROWS: i32 = 24;
+static REPLAY_COLS: i32 = 80;
+// printed by --help
+static USAGE_STR: &str = "Usage: cdls [OPTION]
+       cdls jump <QUERY>
+       cdls import <zoxide|autojump|z> [FILE]\n
 Options:
-\t-h, --help\t\t\tHelp message\n
-Operations in cdls screen:
+\t-h, --help\t\t\tHelp message
+\t--keys <SCRIPT>\t\t\tReplay keys on a screen in memory, like `jj<Right><CR>`
+\t--keys-file <FILE>\t\tReplay the keys of a file, its line breaks are ignored
+\t--dump\t\t\t\tPrint the screen when the replay ends
+\t--size <COLSxROWS>\t\tScreen size of the replay, 80x24 by default
+\t--list [DIR]\t\t\tPrint the items of DIR, the current directory by default, and quit.
+\t\t\t\t\tThis is the default when stdout is not a terminal
+\t--format <FORMAT>\t\tFormat of the list: text, json, csv or ndjson\n
+Commands:
+\tjump <QUERY>\t\t\tJump to the best matching directory visited before
+\timport <TOOL> [FILE]\t\tImport the database of zoxide, autojump or z
+";
+// printed by --help after the usage, and drawn by the help screen
+static KEYS_HELP_STR: &str = "Operations in cdls screen:
 1. Use arrow button to navigate in directory
 \tLeft arrow\t\tGo to parent directory
 \tRight arrow\t\tGo to child directory
 \tUp arrow\t\tGo to previous item
 \tDown arrow\t\tGo to next item
+\tj / k\t\t\tGo to next / previous item
+\tPage Down / Page Up\tGo one page down / up, also ctrl-f / ctrl-b
+\tctrl-d / ctrl-u\t\tGo half a page down / up
+\tHome / End\t\tGo to the first / last item, also g / G
+\t<N>G, :<N>\t\tGo to item number N
+\tA number before a movement repeats it, like `10j` going 10 items down
+\tctrl-z\t\t\tSuspend cdls, `fg` in the shell resumes it
+\tOther letters\t\tGo to the next item starting with the letters
+\tNames can't be typed if they start with a digit or an operation key:
+\tq c f / F h s z j k g G or `:`, search for them with `f`
 2. Enter button\t\t\tExit cdls and jump to current directory
 3. Configuration Screen
 \tc\t\t\tColumn Display
@@ -42,483 +102,559 @@ Operations in cdls screen:
 \tIn configuration screen, use `arrow buttons` to navigate in configuration, use `space` to select, and use `q` to confirm.
 4. Search Mode
 \tf\t\t\tStart search mode
-\tIn search mode, type the keywowrds, the item with better matching will rank higher. Use `up/down` to select items, use `enter` to exit search mode.
+\tIn search mode, type the keywowrds, the item with better matching will rank higher. Use `up/down` to select items.
+\tUse `enter` to accept the search, it keeps applying until `esc` in the main screen. Use `esc` to cancel the search.
+\tEdit keywords with `left/right`, `home/end`, `ctrl-a/ctrl-e`, `ctrl-w` (delete word), `ctrl-u` (delete to start), `ctrl-k` (delete to end).
+\t/\t\t\tStart search mode, hiding items that don't match
+\tIn search mode, use `tab` to switch between hiding and showing the items that don't match.
+\tUse `ctrl-r` to switch the search algorithm: trigram, fuzzy, substring, prefix, glob or regex. Uppercase keywords make the search case sensitive.
+\tUse `ctrl-t` to switch ignoring accents, like `e` matching `é`.
+\tUse `ctrl-p` to switch matching Chinese names by pinyin, like `bg` matching `报告.docx`.
+\tF\t\t\tStart recursive search mode
+\tIn recursive search mode, the keywords are matched against the paths of all items below current directory. Use `enter` to go to the selected item, use `esc` to cancel.
+5. Jump Mode
+\tz\t\t\tJump to a directory visited before
+\tDirectories selected with `enter` are recorded. In jump mode, type the keywords to rank them, use `enter` to go to the selected directory, use `esc` to cancel.
 ";
 
-struct CdlsConfig {
-    item_type: bool,
-    permission: bool,
-    size: bool,
-    mtime: bool,
-    sortby: SortBy,
-    search_mode: bool,
-    search_string: String,
-}
-
-struct CdlsCurPosition {
-    cur_dir: PathBuf,
-    cur_item: PathBuf,
-}
-
-#[derive(Debug, EnumIter, PartialEq, Eq, PartialOrd, Copy, Clone)]
-enum SortBy {
-    Filename,
-    ItemType,
-    Size,
-    MTime,
-}
-
-impl fmt::Display for SortBy {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            SortBy::Filename => write!(f, "File Name"),
-            SortBy::ItemType => write!(f, "Item Type"),
-            SortBy::Size => write!(f, "Size"),
-            SortBy::MTime => write!(f, "Modification Time"),
-        }
+/// Read a key, or `NoKey` after the `backend::timeout` passes.
+///
+/// A screen to draw again after a signal is a `KEY_RESIZE`.
+fn poll_key() -> WideKey {
+    match backend::read_key() {
+        WideKey::NoKey => {
+            if terminal::handle_signals() {
+                WideKey::Code(backend::KEY_RESIZE)
+            } else if script::is_active() {
+                // the replayed keys ran out
+                script::finish(None);
+            } else {
+                WideKey::NoKey
+            }
+        },
+        key => key,
     }
 }
 
-impl SortBy {
-    fn to_usize(&self) -> usize {
-        match self {
-            SortBy::Filename => 0,
-            SortBy::ItemType => 1,
-            SortBy::Size => 2,
-            SortBy::MTime => 3,
+/// Wait for a key, handling the signals received meanwhile.
+fn wait_key() -> WideKey {
+    backend::timeout(SIGNAL_POLL_MS);
+    loop {
+        let key = poll_key();
+        if key != WideKey::NoKey {
+            backend::timeout(-1);
+            return key;
         }
     }
 }
 
-trait PathBufExt {
-    fn file_size(&self) -> u64;
-    fn file_modified_time(&self) -> DateTime<Utc>;
-    fn file_type(&self) -> &str;
-    fn fuzzy_search_score(&self, search_str: &str) -> f32;
+/// Pass through a key read on a screen showing `cur_position`, clearing the status: it shows
+/// until the next key is handled, in every mode. No key or a resize keeps it.
+fn take_key(cur_position: &mut CdlsCurPosition, key: WideKey) -> WideKey {
+    if key != WideKey::NoKey && key.code() != backend::KEY_RESIZE {
+        cur_position.status = None;
+    }
+    return key;
 }
 
-impl PathBufExt for PathBuf {
-    fn file_size(&self) -> u64 {
-        match fs::symlink_metadata(self) {
-            Ok(md) => {
-                return md.len();
-            }
-            Err(_) => {
-                return 0;
+/// `text` cut in screen rows of `width` cells, tabs expanded to the next multiple of 8.
+fn wrap_rows(text: &str, width: usize) -> Vec<String> {
+    let mut rows = Vec::new();
+    for line in text.lines() {
+        let mut expanded = String::new();
+        for c in line.chars() {
+            if c == '\t' {
+                expanded.push_str(&" ".repeat(8 - expanded.width() % 8));
+            } else {
+                expanded.push(c);
             }
         }
-    }
 
-    fn file_modified_time(&self) -> DateTime<Utc> {
-        match fs::symlink_metadata(self) {
-            Ok(md) => {
-                match md.modified() {
-                    Ok(time) => {
-                        return time.into();
-                    }
-                    Err(_) => {
-                        return DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(0, 0), Utc);
-                    }
-                }
-            }
-            Err(_) => {
-                return DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(0, 0), Utc);
+        let mut row = String::new();
+        for c in expanded.chars() {
+            if row.width() + c.width().unwrap_or(0) > width && !row.is_empty() {
+                rows.push(std::mem::take(&mut row));
             }
+            row.push(c);
         }
+        rows.push(row);
     }
+    return rows;
+}
 
-    fn file_type(&self) -> &str {
-        let metadata;
-
-        match fs::symlink_metadata(self) {
-            Ok(md) => {
-                metadata = md;
-            }
-            Err(_) => {
-                return "NO-PERMISSION"
-            }
-        }    
-    
-        let file_type = metadata.file_type();
-        if file_type.is_dir() {
-            return "DIR";
-        }  else if file_type.is_symlink() {
-            return "SYMLINK";
-        } else if file_type.is_socket() || file_type.is_fifo() {
-            return "FD";
-        } else if file_type.is_block_device() || file_type.is_char_device() {
-            return "DEV";
-        } else if file_type.is_file() {
-            return "FILE";
-        } else {
-            return "UNKNOWN";
-        }
+/// Draw the key help from row `offset` of it, clamped so the last page is full.
+///
+/// Return the offset drawn from.
+fn help_screen(offset: usize) -> usize {
+    if draw_too_small() {
+        return offset;
+    }
+    let (maxy, _) = screen_size();
+    let rows = wrap_rows(KEYS_HELP_STR, screen_right() as usize);
+    let page = if show_bars() { maxy - 1 } else { maxy } as usize;
+    let offset = offset.min(rows.len().saturating_sub(page));
+
+    backend::clear();
+    for (y, row) in rows.iter().skip(offset).take(page).enumerate() {
+        backend::mvaddstr(y as i32, 0, row);
     }
 
-    fn fuzzy_search_score(&self, search_str: &str) -> f32 {
-        let file_name = match self.file_name() {
-            Some(name) => {
-                match name.to_str() {
-                    Some(name_str) => {
-                        name_str
-                    },
-                    None => {
-                        ""
-                    }
-                }
-            },
-            None => {
-                ""
-            }
-        };
-        return fuzzy_compare(search_str, file_name);
+    if rows.len() > page {
+        draw_footer(&format!("Up/Down, PageUp/PageDown: Scroll {}/{}; Other keys: Continue",
+            offset + page.min(rows.len()), rows.len()));
+    } else {
+        draw_footer("Press any key to continue");
     }
-}
 
+    backend::refresh();
+    return offset;
+}
 
-trait I32Ext {
-    fn within_u8_range(&self) -> bool;
-    fn to_char(&self) -> char;
-    fn to_u8(&self) -> u8;
+/// The columns enabled in the config which fit in the terminal.
+struct ColumnLayout {
+    item_type: bool,
+    permission: bool,
+    size: bool,
+    mtime: bool,
 }
 
-impl I32Ext for i32 {
-    fn within_u8_range(&self) -> bool {
-        return *self >= 0 && *self <= 255;
-    }
-    fn to_char(&self) -> char {
-        let tmp: u8;
-        if self < &0 {
-            tmp = 0;
-        } else if self > &255 {
-            tmp = 255;
-        } else {
-            tmp = *self as u8;
-        }
+impl ColumnLayout {
+    /// Drop the enabled columns, the least useful first, until the name gets `NAME_MIN_WIDTH` cells.
+    fn fit(cdls_cfg: &CdlsConfig, width: i32) -> ColumnLayout {
+        let mut columns = ColumnLayout {
+            item_type: cdls_cfg.item_type,
+            permission: cdls_cfg.permission,
+            size: cdls_cfg.size,
+            mtime: cdls_cfg.mtime,
+        };
 
-        return tmp as char;
-    }
-    fn to_u8(&self) -> u8 {
-        let tmp: u8;
-        if self < &0 {
-            tmp = 0;
-        } else if self > &255 {
-            tmp = 255;
-        } else {
-            tmp = *self as u8;
+        let name_width = |c: &ColumnLayout| {
+            width - MARKER_WIDTH - TYPE_WIDTH * c.item_type as i32 - PERMISSION_WIDTH * c.permission as i32
+                - SIZE_WIDTH * c.size as i32 - MTIME_WIDTH * c.mtime as i32
+        };
+        if name_width(&columns) < NAME_MIN_WIDTH {
+            columns.mtime = false;
+        }
+        if name_width(&columns) < NAME_MIN_WIDTH {
+            columns.size = false;
+        }
+        if name_width(&columns) < NAME_MIN_WIDTH {
+            columns.permission = false;
+        }
+        if name_width(&columns) < NAME_MIN_WIDTH {
+            columns.item_type = false;
         }
 
-        return tmp;
+        return columns;
     }
 }
 
-fn get_current_dir_element(cur_position: &mut CdlsCurPosition, cdls_cfg: &CdlsConfig) -> Vec<PathBuf> {
-    let mut children = Vec::new();
+/// The text and width in cells of each column before the name.
+fn get_item_row_cells<'a>(columns: &ColumnLayout, marker: &'a str, file_type: &'a str, permissions: &'a str,
+        size: &'a str, mtime: &'a str) -> Vec<(&'a str, i32)> {
+    let mut cells = vec![(marker, MARKER_WIDTH)];
 
-    let read_dir_iter = fs::read_dir(cur_position.cur_dir.clone());
-    match read_dir_iter {
-        Ok(_) => {},
-        Err(e) => {
-            log::warn!("read_dir_iter error: {}", e);
-            return children;
-        }
+    if columns.item_type {
+        cells.push((file_type, TYPE_WIDTH));
     }
-
-    for f in read_dir_iter.unwrap() {
-        match f {
-            Ok(file) => {
-                children.push(file.path());
-            },
-            Err(e) => {
-                log::warn!("error: {}", e);
-                continue;
-            }
-        }
+    if columns.permission {
+        cells.push((permissions, PERMISSION_WIDTH));
+    }
+    if columns.size {
+        cells.push((size, SIZE_WIDTH));
+    }
+    if columns.mtime {
+        cells.push((mtime, MTIME_WIDTH));
     }
 
-    if cdls_cfg.search_mode {
-        children.sort_by(
-            |a, b| 
-            b.fuzzy_search_score(&cdls_cfg.search_string)
-            .total_cmp(&a.fuzzy_search_score(&cdls_cfg.search_string))
-        )
-    } else {
-        match cdls_cfg.sortby {
-            SortBy::Filename => children.sort_by(|a, b| a.cmp(&b)),
-            SortBy::Size => children.sort_by(|a, b| a.file_size().cmp(&b.file_size())),
-            SortBy::MTime => children.sort_by(|a, b| a.file_modified_time().cmp(&b.file_modified_time())),
-            SortBy::ItemType => children.sort_by(|a, b| a.file_type().cmp(&b.file_type())),
-            //_ => {}, 
+    return cells;
+}
+
+/// Draw `cells` side by side on `row`, each placed at its own cell position, so a character
+/// the terminal draws wider or narrower than expected never shifts the next column.
+///
+/// Return the column after the last cell.
+fn draw_row_cells(row: i32, cells: &[(&str, i32)], right: i32) -> i32 {
+    let mut x = 0;
+    for (text, width) in cells {
+        if x >= right {
+            break;
         }
+        // the text is cut to leave at least one blank cell before the next column, and padded
+        // to the column width, so a highlighted row stays one bar
+        backend::mv(row, x);
+        addstr_clipped(text, right.min(x + width - 1));
+        let padding = (right.min(x + width) - backend::getcurx()).max(0);
+        backend::addstr(&" ".repeat(padding as usize));
+        x += width;
     }
+    return x;
+}
 
-    if children.len() >= 1 && cur_position.cur_dir == cur_position.cur_item {
-        // cur_item not set. set it to the first item
-        cur_position.cur_item = children[0].clone();
-        log::warn!("set current postion: {}", cur_position.cur_item.display());
+/// Draw `text` at the cursor, cut before column `right` instead of wrapping to the next row.
+///
+/// Return false if `text` was cut.
+fn addstr_clipped(text: &str, right: i32) -> bool {
+    let mut x = backend::getcurx();
+    let mut end = 0;
+    for (i, c) in text.char_indices() {
+        let w = if c == '\t' { 8 - x % 8 } else { c.width().unwrap_or(0) as i32 };
+        if x + w > right {
+            backend::addstr(&text[..end]);
+            return false;
+        }
+        x += w;
+        end = i + c.len_utf8();
     }
-
-    return children;
+    backend::addstr(text);
+    return true;
 }
 
-fn get_file_metadata_element(path: &PathBuf) -> (String, String, String) {
-
-    let metadata;
+/// Right edge of the drawable area. The last column is left blank, so the cursor never wraps.
+fn screen_right() -> i32 {
+    let (_, maxx) = screen_size();
+    return maxx - 1;
+}
 
-    match fs::symlink_metadata(path) {
-        Ok(md) => {
-            metadata = md;
+/// Draw the item name in `width` cells, with the characters at `positions` made bold and underlined.
+///
+/// Return false if the name was cut with an ellipsis.
+fn draw_item_name(file_name: &str, positions: &[usize], width: usize, style: Ellipsis) -> bool {
+    let (clusters, cut) = truncate::truncate_name(file_name, width, style);
+
+    let match_attr = backend::A_BOLD | backend::A_UNDERLINE;
+    for (i, cluster) in clusters {
+        let is_match = match i {
+            Some(i) => positions.contains(&i),
+            None => false,
+        };
+        if is_match {
+            backend::attron(match_attr);
         }
-        Err(_) => {
-            return (String::from("UNKNOWN"), String::from("UNKNOWN"), String::from("UNKNOWN"));
+        backend::addstr(&cluster);
+        if is_match {
+            backend::attroff(match_attr);
         }
     }
+    return !cut;
+}
 
-    let permissions = metadata.permissions();
-    let mode = permissions.mode();
-    let size = metadata.len();
+fn main_screen_update(cur_position: &mut CdlsCurPosition, cdls_cfg: &CdlsConfig) 
+        -> (Vec<PathBuf>, usize) {
+    // todo: display file owner
 
-    let mut permission_str = String::from("rwxrwxrwx");
-    
-    for i in 0..9 {
-        if mode & (1 << (8 - i)) == 0 {
-            permission_str.replace_range(i..i+1, "-");
-        }
-    }
-    
-    let size_str = size.to_string(); 
+    let (dir_children, total) = list_current_dir(cur_position, cdls_cfg);
 
-    let modified_time_str = match metadata.modified() {
-        Ok(time) => {
-            let datetime: DateTime<Local> = time.into();
-            datetime.format("%Y-%m-%d %H:%M:%S").to_string()    
-        },
-        Err(_) => String::from("UNKNOWN")
+    let matcher = if cdls_cfg.search_mode {
+        Some(new_matcher(cdls_cfg, &cdls_cfg.search_string))
+    } else {
+        None
     };
 
-    return (permission_str, size_str, modified_time_str);
-}
-
-fn help_screen(maxy: i32) {
-    ncurses::mv(0, 0);
-    ncurses::addstr(HELP_STR);
+    let count_str = if cdls_cfg.search_filter {
+        format!("{} of {}", dir_children.len(), total)
+    } else {
+        String::new()
+    };
 
-    ncurses::clrtobot();
+    let bt_str = if cdls_cfg.search_editing && cdls_cfg.search_filter {
+        format!("Search string:{} {}\t{}\tTab: Show all; Ctrl-R: Algorithm; Enter: Accept; Esc: Cancel",
+            cdls_cfg.search_string, search_algo_indicator(matcher.as_ref().unwrap()), count_str)
+    } else if cdls_cfg.search_editing {
+        format!("Search string:{} {}\tTab: Hide non-matches; Ctrl-R: Algorithm; Enter: Accept; Esc: Cancel",
+            cdls_cfg.search_string, search_algo_indicator(matcher.as_ref().unwrap()))
+    } else if cdls_cfg.search_mode {
+        format!("Search:{} {}\t{}\tEsc: Clear search; f: Edit search; Enter: Quit cdls and jump to selected item",
+            cdls_cfg.search_string, search_algo_indicator(matcher.as_ref().unwrap()), count_str)
+    } else {
+        String::from("Arrow Keys: Select item; Enter: Quit cdls and jump to selected item; h: More help")
+    };
 
-    ncurses::attron(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
-    ncurses::mvaddstr(maxy - 1, 0, "Press any key to continue");
-    ncurses::attroff(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
-    
-    ncurses::refresh();
+    let placeholder = if total == 0 { "(empty)" } else { "(no matches)" };
+    return item_list_screen_update(cur_position, cdls_cfg, dir_children, matcher.as_ref(), &bt_str, placeholder);
 }
 
-fn get_item_row_str(cdls_cfg: &CdlsConfig, file_type: &str, permissions: &str, size: &str, file_name: &str, mtime: &str) -> String {
-
-    let mut row_str:String;
-
-    if cdls_cfg.item_type {
-        row_str = format!("{:<8}", file_type);
+fn search_algo_indicator(matcher: &Matcher) -> String {
+    let accent_str = if matcher.ignore_diacritics { ", ignore accents" } else { "" };
+    let pinyin_str = if matcher.pinyin { ", pinyin" } else { "" };
+    if matcher.is_valid() {
+        return format!("[{}{}{}]", matcher.algo, accent_str, pinyin_str);
     } else {
-        row_str = String::from("");
-    } 
-
-    if cdls_cfg.permission {
-        row_str = format!("{}{:<16}", row_str, permissions);
+        return format!("[{}{}{}: invalid]", matcher.algo, accent_str, pinyin_str);
     }
+}
 
-    if cdls_cfg.size {
-        row_str = format!("{}{:<16}", row_str, size);
+/// First item shown in a list of `len` items with `rows` visible rows, keeping the cursor
+/// `scrolloff` rows away from the edges, or in the middle if `center` is set.
+fn scroll_viewport(offset: usize, cursor: usize, len: usize, rows: usize, scrolloff: usize, center: bool) -> usize {
+    if len <= rows || rows == 0 {
+        return 0;
     }
+    let max_offset = len - rows;
 
-    if cdls_cfg.mtime {
-        row_str = format!("{}{:<24}", row_str, mtime);
+    if center {
+        return cursor.saturating_sub(rows / 2).min(max_offset);
     }
 
-    row_str = format!("{}{}\n", row_str, file_name);
-
-    return row_str;
+    let margin = scrolloff.min((rows - 1) / 2);
+    let mut offset = offset.min(max_offset);
+    if cursor < offset + margin {
+        offset = cursor.saturating_sub(margin);
+    }
+    if cursor + margin >= offset + rows {
+        offset = cursor + margin + 1 - rows;
+    }
+    return offset.min(max_offset);
 }
 
-fn main_screen_update(cur_position: &mut CdlsCurPosition, maxy: i32, cdls_cfg: &CdlsConfig) 
-        -> (Vec<PathBuf>, usize) {
-    // todo: display file owner
-    // todo: screen height limit, if too small, prompt.  maxy < 3
-
-    ncurses::clear();
-    ncurses::mv(0, 0);
-
-    let bar_str = format!("CDLS # {}\n", cur_position.cur_dir.to_str().unwrap());
-    ncurses::addstr(&bar_str);
+/// Draw `dir_children` below the current directory bar, names relative to the current directory.
+/// The characters of names matched by `matcher` are highlighted.
+///
+/// Draw `placeholder` if there are no items. If the name of the selected item is cut, its full
+/// name is drawn above the footer, which shows `bt_str`.
+fn item_list_screen_update(cur_position: &mut CdlsCurPosition, cdls_cfg: &CdlsConfig,
+        dir_children: Vec<PathBuf>, matcher: Option<&Matcher>, bt_str: &str,
+        placeholder: &str) -> (Vec<PathBuf>, usize) {
+    log::info!("cur item: {}", cur_position.cur_item.display());
 
-    let dir_children = get_current_dir_element(cur_position, cdls_cfg);
+    let cursor = dir_children.iter().position(|x| *x == cur_position.cur_item).unwrap_or_default();
+    if draw_too_small() {
+        return (dir_children, cursor);
+    }
 
-    log::info!("cur item: {}", cur_position.cur_item.display());
+    let right = screen_right();
+    let columns = ColumnLayout::fit(cdls_cfg, right);
+    backend::clear();
 
-    let cursor = match dir_children.iter().position(|x| *x == cur_position.cur_item) {
-        Some(pos) => pos,
-        None => 0,
+    // the position goes to the right end of the header, the directory is cut before it
+    let pos_str = if dir_children.is_empty() {
+        String::from("0/0")
+    } else {
+        format!("{}/{}", cursor + 1, dir_children.len())
     };
+    let pos_x = right - pos_str.len() as i32;
+    let bar_str = format!("CDLS # {}", quote::quote_name(cur_position.cur_dir.as_os_str(), cdls_cfg.quoting_style));
+    if show_bars() {
+        backend::mv(0, 0);
+        addstr_clipped(&bar_str, pos_x - 1);
+        backend::mvaddstr(0, pos_x.max(0), &pos_str);
+    }
 
-    let start_idx = if cursor as i32 - maxy as i32 + 4 < 0 {
-        0
-    } else {
-        (cursor as i32 - maxy as i32 + 4) as usize
-    };   
+    let mut rows = page_rows();
+    cur_position.scroll_offset = scroll_viewport(cur_position.scroll_offset, cursor, dir_children.len(),
+        rows, cdls_cfg.scrolloff, cdls_cfg.center_cursor);
 
-    let mut idx = 0;
-    for child in &dir_children {
-        if idx < start_idx {
-            idx += 1;
-            continue;
-        }
+    if dir_children.is_empty() {
+        backend::mv(list_top(), MARKER_WIDTH);
+        backend::attron(backend::A_DIM);
+        addstr_clipped(placeholder, right);
+        backend::attroff(backend::A_DIM);
+    }
 
-        if start_idx > 0 && idx == start_idx {
-            ncurses::addstr("\t...\n");
-            idx += 1;
-            continue;
-        }
-        if idx - start_idx == (maxy - 3) as usize {
-            ncurses::addstr("\t...\n");
-            break;
+    let cut_name = draw_item_rows(cur_position, cdls_cfg, &dir_children, matcher, &columns, cursor, rows);
+    if let Some(name) = cut_name {
+        // the full name of the selected item gets rows of its own above the footer, and the list
+        // is laid out again in the rows left
+        let name_rows = wrap_rows(&name, right as usize);
+        let name_rows = &name_rows[..name_rows.len().min(rows - 1)];
+        if !name_rows.is_empty() {
+            for row in list_top()..list_top() + rows as i32 {
+                backend::mv(row, 0);
+                backend::clrtoeol();
+            }
+            rows -= name_rows.len();
+            cur_position.scroll_offset = scroll_viewport(cur_position.scroll_offset, cursor, dir_children.len(),
+                rows, cdls_cfg.scrolloff, cdls_cfg.center_cursor);
+            draw_item_rows(cur_position, cdls_cfg, &dir_children, matcher, &columns, cursor, rows);
+
+            backend::attron(backend::A_BOLD);
+            for (i, name_row) in name_rows.iter().enumerate() {
+                backend::mvaddstr(list_top() + (rows + i) as i32, 0, name_row);
+            }
+            backend::attroff(backend::A_BOLD);
         }
+    }
+
+    match &cur_position.status {
+        Some(status) => draw_footer(status),
+        None => draw_footer(bt_str),
+    }
+
+    backend::refresh();
 
+    return (dir_children, cursor);
+}
+
+/// Draw `rows` items of `dir_children` from the scroll offset of `cur_position`.
+///
+/// Return the full name of the selected item if it was cut.
+fn draw_item_rows(cur_position: &CdlsCurPosition, cdls_cfg: &CdlsConfig, dir_children: &[PathBuf],
+        matcher: Option<&Matcher>, columns: &ColumnLayout, cursor: usize, rows: usize) -> Option<String> {
+    let right = screen_right();
+    let start_idx = cur_position.scroll_offset;
+    let mut cut_name: Option<String> = None;
+
+    for (idx, child) in dir_children.iter().enumerate().skip(start_idx).take(rows) {
+        let row = (idx - start_idx) as i32 + list_top();
         if idx == cursor {
-            ncurses::attron(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
-        // bug: in Xshell alignment doesn't work;
+            backend::attron(backend::A_HIGHLIGHT);
         } 
 
         let file_path = child.as_path();
 
         let file_type = child.file_type();
-        let mut file_name = child.clone().file_name().expect("").to_str().unwrap().to_string();
+        let rel_name = child.strip_prefix(&cur_position.cur_dir).unwrap_or(child).as_os_str();
+        let file_name = quote::quote_name(rel_name, cdls_cfg.quoting_style);
+        // names are matched escaped, the matched characters are found in the name as it is drawn
+        let positions = match matcher {
+            Some(matcher) => {
+                let matched = matcher.positions(&quote::quote_name(rel_name, QuotingStyle::Escape));
+                quote::map_escape_positions(rel_name, &matched, cdls_cfg.quoting_style)
+            },
+            None => Vec::new(),
+        };
+        let mut link_str = String::new();
         
         if file_type.eq("SYMLINK") {
             let sym_link_to = match fs::read_link(file_path) {
-                Ok(link_to) => {
-                    match link_to.to_str() {
-                        Some(link_str) => String::from(link_str),
-                        _ => String::from("")
-                    }
-                },
+                Ok(link_to) => quote::quote_name(link_to.as_os_str(), cdls_cfg.quoting_style),
                 Err(_) => String::from("")
             };
 
-            file_name.push_str(" -> ");
-            file_name.push_str(&sym_link_to);
+            link_str.push_str(" -> ");
+            link_str.push_str(&sym_link_to);
         }
 
         let (permissions, size, mtime) = get_file_metadata_element(child);
         
-        let mut row_str = get_item_row_str(cdls_cfg, file_type, &permissions, &size, &file_name, &mtime);
-
-        if idx == cursor {
-            row_str.insert_str(0, ">>>>\t");
-        } else {
-            row_str.insert_str(0, "    \t");
+        let marker = if idx == cursor { ">>>>" } else { "" };
+        let cells = get_item_row_cells(columns, marker, file_type, &permissions, &size, &mtime);
+        let name_x = draw_row_cells(row, &cells, right);
+
+        if name_x < right {
+            // the name goes first, the link target gets the cells left
+            backend::mv(row, name_x);
+            let avail = (right - name_x) as usize;
+            let name_width = file_name.width();
+            let fits = if name_width + link_str.width() <= avail {
+                draw_item_name(&file_name, &positions, avail, cdls_cfg.name_ellipsis);
+                addstr_clipped(&link_str, right);
+                true
+            } else if !link_str.is_empty() && name_width + 8 <= avail {
+                draw_item_name(&file_name, &positions, avail, cdls_cfg.name_ellipsis);
+                let (clusters, _) = truncate::truncate_name(&link_str, avail - name_width, Ellipsis::End);
+                for (_, cluster) in clusters {
+                    backend::addstr(&cluster);
+                }
+                false
+            } else {
+                draw_item_name(&file_name, &positions, avail, cdls_cfg.name_ellipsis);
+                false
+            };
+            if !fits && idx == cursor {
+                cut_name = Some(format!("{}{}", file_name, link_str));
+            }
         }
 
-        ncurses::addstr(&row_str);
-
         if idx == cursor {
-            ncurses::attroff(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
-        // bug: in Xshell alignment doesn't work;
+            backend::attroff(backend::A_HIGHLIGHT);
         }
+    }
 
-        idx += 1;
-    } 
-
-    // ncurses::clrtobot();
-
-    let bt_str: String;
-    if cdls_cfg.search_mode {
-        bt_str = format!("Search string:{} \tEnter: Exit search mode", cdls_cfg.search_string);
-    } else {
-        bt_str = String::from("Arrow Keys: Select item; Enter: Quit cdls and jump to selected item; h: More help");
-    };
-    
-    ncurses::attron(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
-    ncurses::mvaddstr(maxy - 1, 0, &bt_str);
-    ncurses::attroff(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
-
-    ncurses::refresh();
+    return cut_name;
+}
 
-    return (dir_children, cursor);
+/// Draw the highlighted bottom bar, cut at the right edge of the screen.
+fn draw_footer(bt_str: &str) {
+    if !show_bars() {
+        return;
+    }
+    let (maxy, _) = screen_size();
+    backend::attron(backend::A_HIGHLIGHT);
+    backend::mv(maxy - 1, 0);
+    // text drawn past the list, like a long help, doesn't show through
+    backend::clrtoeol();
+    addstr_clipped(bt_str, screen_right());
+    backend::attroff(backend::A_HIGHLIGHT);
 }
 
 fn print_help() {
-    println!("{}", HELP_STR);
+    println!("{}\n{}", USAGE_STR, KEYS_HELP_STR);
 }
 
-fn column_cfg_screen_update(maxy: i32, cdls_cfg: &CdlsConfig, selected: usize) {
-    ncurses::clear();
-    ncurses::mv(0, 0);
+fn column_cfg_screen_update(cdls_cfg: &CdlsConfig, selected: usize) {
+    if draw_too_small() {
+        return;
+    }
+    backend::clear();
+    backend::mv(0, 0);
 
-    ncurses::addstr("Please Select Columns to Display\n");
+    if show_bars() {
+        backend::addstr("Please Select Columns to Display\n");
+    }
 
     if selected == 0 {
-        ncurses::attron(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
+        backend::attron(backend::A_HIGHLIGHT);
     }
     if cdls_cfg.item_type {
-        ncurses::addstr("* Item Type\n");
+        backend::addstr("* Item Type\n");
     } else {
-        ncurses::addstr("  Item Type\n");
+        backend::addstr("  Item Type\n");
     }
     if selected == 0 {
-        ncurses::attroff(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
+        backend::attroff(backend::A_HIGHLIGHT);
     }
 
     if selected == 1 {
-        ncurses::attron(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
+        backend::attron(backend::A_HIGHLIGHT);
     }
     if cdls_cfg.permission {
-        ncurses::addstr("* Permission\n");
+        backend::addstr("* Permission\n");
     } else {
-        ncurses::addstr("  Permission\n");
+        backend::addstr("  Permission\n");
     }
     if selected == 1 {
-        ncurses::attroff(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
+        backend::attroff(backend::A_HIGHLIGHT);
     }
 
     if selected == 2 {
-        ncurses::attron(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
+        backend::attron(backend::A_HIGHLIGHT);
     }
     if cdls_cfg.size {
-        ncurses::addstr("* Size\n");
+        backend::addstr("* Size\n");
     } else {
-        ncurses::addstr("  Size\n");
+        backend::addstr("  Size\n");
     }
     if selected == 2 {
-        ncurses::attroff(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
+        backend::attroff(backend::A_HIGHLIGHT);
     }
 
     if selected == 3 {
-        ncurses::attron(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
+        backend::attron(backend::A_HIGHLIGHT);
     }
     if cdls_cfg.mtime {
-        ncurses::addstr("* Modification Time\n");
+        backend::addstr("* Modification Time\n");
     } else {
-        ncurses::addstr("  Modification Time\n");
+        backend::addstr("  Modification Time\n");
     }
     if selected == 3 {
-        ncurses::attroff(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
+        backend::attroff(backend::A_HIGHLIGHT);
     }
 
     let bt_str = "Space: Toggle Selection; q: Save and Quit";
-    ncurses::attron(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
-    ncurses::mvaddstr(maxy - 1, 0, bt_str);
-    ncurses::attroff(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
+    draw_footer(bt_str);
 
-    ncurses::refresh();
+    backend::refresh();
 }
 
-fn column_cfg(maxy: i32, cdls_cfg: &mut CdlsConfig) {
+fn column_cfg(cdls_cfg: &mut CdlsConfig) {
    
     let mut selected: usize = 0;
 
-    column_cfg_screen_update(maxy, cdls_cfg, selected);
+    column_cfg_screen_update(cdls_cfg, selected);
 
     loop {
-        let ch = ncurses::getch();
+        let ch = wait_key().code();
         
         match ch {
             32 => { /* space */
@@ -529,279 +665,961 @@ fn column_cfg(maxy: i32, cdls_cfg: &mut CdlsConfig) {
                     3 => cdls_cfg.mtime = !cdls_cfg.mtime,
                     _ => {}
                 }
-                column_cfg_screen_update(maxy, cdls_cfg, selected);
+                column_cfg_screen_update(cdls_cfg, selected);
             },
             113 => { // q
                 return;
             },
-            ncurses::KEY_UP => {
-                if selected > 0 {
-                    selected -= 1;
-                }
-                column_cfg_screen_update(maxy, cdls_cfg, selected);
+            backend::KEY_UP => {
+                selected = selected.saturating_sub(1);
+                column_cfg_screen_update(cdls_cfg, selected);
             },
-            ncurses::KEY_DOWN => {
+            backend::KEY_DOWN => {
                 if selected < 3 {
                     selected += 1;
                 }
-                column_cfg_screen_update(maxy, cdls_cfg, selected);
+                column_cfg_screen_update(cdls_cfg, selected);
             },
             _ => {
-                column_cfg_screen_update(maxy, cdls_cfg, selected);
+                column_cfg_screen_update(cdls_cfg, selected);
             }
         }
     }
 
 }
 
-fn sort_cfg_screen_update(maxy: i32, cdls_cfg: &CdlsConfig, selected: &SortBy) {
-    ncurses::clear();
-    ncurses::mv(0, 0);
+fn sort_cfg_screen_update(cdls_cfg: &CdlsConfig, selected: &SortBy) {
+    if draw_too_small() {
+        return;
+    }
+    backend::clear();
+    backend::mv(0, 0);
 
-    ncurses::addstr("Sort tht items by:\n");
+    if show_bars() {
+        backend::addstr("Sort tht items by:\n");
+    }
     
     for sortby in SortBy::iter() {
         if selected.to_usize() == sortby.to_usize() {
-            ncurses::attron(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
+            backend::attron(backend::A_HIGHLIGHT);
         }
         if cdls_cfg.sortby == sortby {
-            ncurses::addstr(&format!("* {}\n", sortby.to_string()));
+            backend::addstr(&format!("* {}\n", sortby));
         } else {
-            ncurses::addstr(&format!("  {}\n", sortby.to_string()));
+            backend::addstr(&format!("  {}\n", sortby));
         }
         if selected.to_usize() == sortby.to_usize() {
-            ncurses::attroff(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
+            backend::attroff(backend::A_HIGHLIGHT);
         }
     }
 
     let bt_str = "Space: Toggle Selection; q: Save and Quit";
-    ncurses::attron(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
-    ncurses::mvaddstr(maxy - 1, 0, bt_str);
-    ncurses::attroff(ncurses::COLOR_PAIR(COLOR_PAIR_HIGHLIGHT));
+    draw_footer(bt_str);
 
-    ncurses::refresh();
+    backend::refresh();
 }
 
-fn sort_cfg(maxy: i32, cdls_cfg: &mut CdlsConfig) {
+fn sort_cfg(cdls_cfg: &mut CdlsConfig) {
        
     let mut selected: SortBy = SortBy::iter().nth(cdls_cfg.sortby.to_usize()).unwrap();
 
-    sort_cfg_screen_update(maxy, cdls_cfg, &selected);
+    sort_cfg_screen_update(cdls_cfg, &selected);
 
     loop {
-        let ch = ncurses::getch();
+        let ch = wait_key().code();
         
         match ch {
             32 => { /* space */
                 cdls_cfg.sortby = selected;
-                sort_cfg_screen_update(maxy, cdls_cfg, &selected);
+                sort_cfg_screen_update(cdls_cfg, &selected);
             },
             113 => { // q
                 return;
             },
-            ncurses::KEY_UP => {
+            backend::KEY_UP => {
                 if selected > SortBy::Filename {
                     selected = SortBy::iter().nth(selected.to_usize() - 1).unwrap();
                 }
-                sort_cfg_screen_update(maxy, cdls_cfg, &selected);
+                sort_cfg_screen_update(cdls_cfg, &selected);
             },
-            ncurses::KEY_DOWN => {
+            backend::KEY_DOWN => {
                 if selected < SortBy::MTime {
                     selected = SortBy::iter().nth(selected.to_usize() + 1).unwrap();
                 }
-                sort_cfg_screen_update(maxy, cdls_cfg, &selected);
+                sort_cfg_screen_update(cdls_cfg, &selected);
             },
             _ => {
-                sort_cfg_screen_update(maxy, cdls_cfg, &selected);
+                sort_cfg_screen_update(cdls_cfg, &selected);
             }
         }
     }
 }
 
-fn search_mode(cur_position: &mut CdlsCurPosition, maxy: i32, cdls_cfg: &mut CdlsConfig) {
+/// Highlight the input cursor of the bottom bar, which starts with `prefix` and `line`.
+fn draw_input_cursor(prefix: &str, line: &str, cursor: usize) {
+    if !show_bars() {
+        return;
+    }
+    let (maxy, _) = screen_size();
+    let before_cursor: String = line.chars().take(cursor).collect();
+    let col = prefix.width() + before_cursor.width();
+    backend::mvchgat(maxy - 1, col as i32, 1, backend::A_REVERSE | backend::A_HIGHLIGHT);
+    backend::refresh();
+}
+
+fn search_mode(cur_position: &mut CdlsCurPosition, cdls_cfg: &mut CdlsConfig, filter: bool) {
+    // restored by esc
+    let saved_item = cur_position.cur_item.clone();
+    let saved_mode = cdls_cfg.search_mode;
+    let saved_filter = cdls_cfg.search_filter;
+    let saved_string = cdls_cfg.search_string.clone();
 
     cdls_cfg.search_mode = true;
+    cdls_cfg.search_editing = true;
+    cdls_cfg.search_filter = filter;
+    cdls_cfg.search_cursor = cdls_cfg.search_string.chars().count();
 
-    while cdls_cfg.search_mode {
-        let (dir_children, cursor) = main_screen_update(cur_position, maxy, &cdls_cfg);
+    loop {
+        let (dir_children, cursor) = main_screen_update(cur_position, cdls_cfg);
+        draw_input_cursor("Search string:", &cdls_cfg.search_string, cdls_cfg.search_cursor);
 
-        let ch = ncurses::getch();
-        log::debug!("press {}", ch);
+        let key = take_key(cur_position, wait_key());
+        let ch = key.code();
+        log::debug!("press {:?}", key);
 
-        if ch.within_u8_range() && is_printable(ch.to_u8())
-                || ch == ncurses::KEY_BACKSPACE 
-                || ch == 8 /* backspace */ {
-            // reset cursor
-            cur_position.cur_item = cur_position.cur_dir.clone();
+        let old_string = cdls_cfg.search_string.clone();
+        if line_edit::edit_line(&mut cdls_cfg.search_string, &mut cdls_cfg.search_cursor, key) {
+            if cdls_cfg.search_string != old_string {
+                // reset cursor
+                cur_position.cur_item = cur_position.cur_dir.clone();
+            }
+            continue;
+        }
+
+        match ch {
+            backend::KEY_UP => {
+                if cursor > 0 {
+                    cur_position.cur_item = dir_children[cursor - 1].clone();
+                }
+            },
+            backend::KEY_DOWN => {
+                if cursor + 1 < dir_children.len() {
+                    cur_position.cur_item = dir_children[cursor + 1].clone();
+                }
+            },
+            9 => { // tab
+                cdls_cfg.search_filter = !cdls_cfg.search_filter;
+            },
+            18 => { // ctrl-r
+                cdls_cfg.search_algo = cdls_cfg.search_algo.next();
+                cur_position.cur_item = cur_position.cur_dir.clone();
+            },
+            20 => { // ctrl-t
+                cdls_cfg.search_ignore_diacritics = !cdls_cfg.search_ignore_diacritics;
+                cur_position.cur_item = cur_position.cur_dir.clone();
+            },
+            16 => { // ctrl-p
+                cdls_cfg.search_pinyin = !cdls_cfg.search_pinyin;
+                cur_position.cur_item = cur_position.cur_dir.clone();
+            },
+            10 | backend::KEY_ENTER => { // enter
+                cdls_cfg.search_editing = false;
+                match dir_children.get(cursor) {
+                    Some(child) if cdls_cfg.search_enter_dir && child.is_dir() => {
+                        cur_position.cur_dir = child.clone();
+                        cur_position.cur_item = child.clone();
+                        clear_search(cdls_cfg);
+                    },
+                    _ => {
+                        if cdls_cfg.search_enter_dir || cdls_cfg.search_string.is_empty() {
+                            clear_search(cdls_cfg);
+                        }
+                        // otherwise the search keeps applying, until esc in the main screen
+                    }
+                }
+                break;
+            },
+            27 => { // esc
+                cdls_cfg.search_mode = saved_mode;
+                cdls_cfg.search_editing = false;
+                cdls_cfg.search_filter = saved_filter;
+                cdls_cfg.search_string = saved_string;
+                cur_position.cur_item = saved_item;
+                break;
+            },
+            _ => {
+                continue;   
+            }
+        }
+    }
+}
+
+fn recursive_search_mode(cur_position: &mut CdlsCurPosition, cdls_cfg: &mut CdlsConfig) {
+    let opts = RecursiveOptions {
+        max_depth: cdls_cfg.recursive_depth,
+        max_results: cdls_cfg.recursive_max_results,
+        ignore: cdls_cfg.recursive_ignore.clone(),
+    };
+
+    let saved_item = cur_position.cur_item.clone();
+    let mut search_string = String::new();
+    let mut search_cursor: usize = 0;
+    let mut search = RecursiveSearch::start(cur_position.cur_dir.clone(),
+        new_matcher(cdls_cfg, &search_string), &opts);
+    cur_position.cur_item = cur_position.cur_dir.clone();
 
-            if ch == ncurses::KEY_BACKSPACE || ch == 8 {
-                cdls_cfg.search_string.pop();
+    let mut dir_children = Vec::new();
+    let mut cursor = 0;
+    let mut redraw = true;
+
+    // poll for results streaming in while waiting for keys
+    backend::timeout(100);
+
+    loop {
+        if search.poll() {
+            redraw = true;
+        }
+
+        if redraw {
+            let results: Vec<PathBuf> = search.results.iter().map(|(path, _)| path.clone()).collect();
+            if !results.is_empty() && cur_position.cur_item == cur_position.cur_dir {
+                // cur_item not set. set it to the best match
+                cur_position.cur_item = results[0].clone();
+            }
+
+            let state_str = if search.done {
+                format!("{} found", results.len())
             } else {
-                cdls_cfg.search_string.push(ch.to_char());
+                format!("{} found, searching...", results.len())
+            };
+            let matcher = new_matcher(cdls_cfg, &search_string);
+            let bt_str = format!("Recursive search:{} {}\t{}\tCtrl-R: Algorithm; Enter: Go to item; Esc: Cancel",
+                search_string, search_algo_indicator(&matcher), state_str);
+
+            (dir_children, cursor) = item_list_screen_update(cur_position, cdls_cfg, results, Some(&matcher), &bt_str,
+                if search.done { "(no matches)" } else { "" });
+            draw_input_cursor("Recursive search:", &search_string, search_cursor);
+            redraw = false;
+        }
+
+        // replayed keys come faster than the walk, wait for all of its results so a replay
+        // always goes the same way
+        if script::is_active() && !search.done {
+            thread::sleep(Duration::from_millis(10));
+            continue;
+        }
+
+        let key = take_key(cur_position, poll_key());
+        let ch = key.code();
+        if ch == backend::ERR {
+            continue;
+        }
+        log::debug!("press {:?}", key);
+        redraw = true;
+
+        let old_string = search_string.clone();
+        let edited = line_edit::edit_line(&mut search_string, &mut search_cursor, key);
+        if edited && search_string == old_string {
+            continue;
+        }
+        if edited || ch == 18 /* ctrl-r */ || ch == 20 /* ctrl-t */ || ch == 16 /* ctrl-p */ {
+            if ch == 18 {
+                cdls_cfg.search_algo = cdls_cfg.search_algo.next();
+            } else if ch == 20 {
+                cdls_cfg.search_ignore_diacritics = !cdls_cfg.search_ignore_diacritics;
+            } else if ch == 16 {
+                cdls_cfg.search_pinyin = !cdls_cfg.search_pinyin;
             }
+
+            // restart the walk, the old one is cancelled on drop
+            search = RecursiveSearch::start(cur_position.cur_dir.clone(),
+                new_matcher(cdls_cfg, &search_string), &opts);
+            cur_position.cur_item = cur_position.cur_dir.clone();
+            continue;
         }
 
         match ch {
-            ncurses::KEY_UP => {
+            backend::KEY_UP => {
                 if cursor > 0 {
                     cur_position.cur_item = dir_children[cursor - 1].clone();
                 }
             },
-            ncurses::KEY_DOWN => {
-                if cursor < dir_children.len() - 1 {
+            backend::KEY_DOWN => {
+                if cursor + 1 < dir_children.len() {
                     cur_position.cur_item = dir_children[cursor + 1].clone();
                 }
             },
-            10 | ncurses::KEY_ENTER => { // enter
-                // exit search mode
-                cdls_cfg.search_mode = false;
-                cdls_cfg.search_string.clear();                    
-                break;     
+            10 | backend::KEY_ENTER => { // enter
+                match dir_children.get(cursor) {
+                    Some(item) => {
+                        // go to the directory of the match, with the cursor on it. An accepted
+                        // search of the main screen would hide it there
+                        clear_search(cdls_cfg);
+                        if let Some(parent) = item.parent() {
+                            cur_position.cur_dir = parent.to_path_buf();
+                        }
+                        cur_position.cur_item = item.clone();
+                    },
+                    None => {
+                        cur_position.cur_item = saved_item;
+                    }
+                }
+                break;
+            },
+            27 => { // esc
+                cur_position.cur_item = saved_item;
+                break;
             },
             _ => {
-                continue;   
+                continue;
             }
         }
     }
+
+    backend::timeout(-1);
 }
 
-fn main() {
-    
-    let args: Vec<String> = env::args().collect();
-    if args.len() > 2 {
-        print_help();
-        exit(0);
+fn jump_screen_update(entries: &[&frecency::FrecencyEntry], cursor: usize, jump_string: &str, quoting: QuotingStyle) {
+    if draw_too_small() {
+        return;
     }
+    backend::clear();
 
-    let mut debug_mode = false;
-    if args.len() == 2 {
-        if args[1] == "--help" || args[1] == "-h" {
-            print_help();            
-            exit(0);
-        } else if args[1] == "--debug" || args[1] == "-d" {
-            debug_mode = true;
-        } else {
-            print_help();
-            exit(0);
+    if show_bars() {
+        backend::mv(0, 0);
+        addstr_clipped("CDLS # Jump to a directory visited before", screen_right());
+    }
+
+    let rows = page_rows();
+    let start_idx = (cursor + 1).saturating_sub(rows);
+
+    for (idx, entry) in entries.iter().enumerate().skip(start_idx).take(rows) {
+        let row = (idx - start_idx) as i32 + list_top();
+        let marker = if idx == cursor { ">>>>" } else { "" };
+        let rank = format!("{:.1}", entry.rank);
+        let path = quote::quote_name(entry.path.as_os_str(), quoting);
+        if idx == cursor {
+            backend::attron(backend::A_HIGHLIGHT);
+        }
+        let right = screen_right();
+        let path_x = draw_row_cells(row, &[(marker, MARKER_WIDTH), (&rank, RANK_WIDTH)], right);
+        if path_x < right {
+            backend::mv(row, path_x);
+            addstr_clipped(&path, right);
+        }
+        if idx == cursor {
+            backend::attroff(backend::A_HIGHLIGHT);
         }
     }
 
-    if debug_mode {
-        match fs::File::create(".cdls.log") {
-            Ok(fd) => {
-                WriteLogger::init(LevelFilter::Debug, Config::default(), fd).unwrap();
+    let bt_str = format!("Jump string:{} \tEnter: Go to directory; Esc: Cancel", jump_string);
+    draw_footer(&bt_str);
+
+    backend::refresh();
+}
+
+fn jump_mode(cur_position: &mut CdlsCurPosition, db: &FrecencyDb, cdls_cfg: &mut CdlsConfig) {
+    let mut jump_string = String::new();
+    let mut jump_cursor: usize = 0;
+    let mut cursor: usize = 0;
+
+    loop {
+        let entries = db.query(&jump_string, cdls_cfg.search_pinyin);
+        jump_screen_update(&entries, cursor, &jump_string, cdls_cfg.quoting_style);
+        draw_input_cursor("Jump string:", &jump_string, jump_cursor);
+
+        let key = take_key(cur_position, wait_key());
+        let ch = key.code();
+        log::debug!("press {:?}", key);
+
+        let old_string = jump_string.clone();
+        if line_edit::edit_line(&mut jump_string, &mut jump_cursor, key) {
+            if jump_string != old_string {
+                cursor = 0;
+            }
+            continue;
+        }
+
+        match ch {
+            backend::KEY_UP => {
+                cursor = cursor.saturating_sub(1);
             },
-            Err(io_error) => {
-                println!("Fail to create log file {}, {}", ".cdls.log", io_error);
-                exit(1);
+            backend::KEY_DOWN => {
+                if cursor + 1 < entries.len() {
+                    cursor += 1;
+                }
             },
-        };  
+            10 | backend::KEY_ENTER => { // enter
+                if let Some(entry) = entries.get(cursor) {
+                    // an accepted search of the main screen would hide the items there
+                    clear_search(cdls_cfg);
+                    cur_position.cur_dir = entry.path.clone();
+                    cur_position.cur_item = entry.path.clone();
+                }
+                return;
+            },
+            27 => { // esc
+                return;
+            },
+            _ => {
+                continue;
+            }
+        }
     }
+}
 
-    let rst = env::current_dir();
-    let cur_path = match rst {
-        Ok(path) => path,
-        Err(e) => {
-            log::error!("Fail to open current directory. {}", e);
-            exit(1);
+fn open_frecency_db() -> Option<FrecencyDb> {
+    match FrecencyDb::default_path() {
+        Some(db_path) => Some(FrecencyDb::load(&db_path)),
+        None => {
+            log::warn!("HOME is not set, frecency database disabled");
+            None
         }
-    };
+    }
+}
 
-    ncurses::initscr();
-    ncurses::keypad(ncurses::stdscr(), true);
-    ncurses::noecho();
-    ncurses::curs_set(ncurses::CURSOR_VISIBILITY::CURSOR_INVISIBLE);
-
-    ncurses::start_color();
-    ncurses::init_pair(COLOR_PAIR_HIGHLIGHT, ncurses::COLOR_BLACK, ncurses::COLOR_WHITE);
-    ncurses::init_pair(COLOR_PAIR_WIN, ncurses::COLOR_BLACK, ncurses::COLOR_CYAN);
-
-    let mut maxy = ncurses::getmaxy(ncurses::stdscr());
-    let mut cdls_cfg = CdlsConfig {
-        item_type: true, 
-        permission: true, 
-        size: true, 
-        mtime: true, 
-        sortby: SortBy::Filename,
-        search_mode: false,
-        search_string: String::new(),
-    };
+fn record_visit(db: &mut Option<FrecencyDb>, dir: &Path) {
+    if let Some(db) = db {
+        db.add(dir);
+        if let Err(e) = db.save() {
+            log::warn!("Fail to save frecency database. {}", e);
+        }
+    }
+}
+
+/// Screen height and width, read at draw time so a resized terminal is laid out again.
+/// The main screen until enter goes to a directory, returned, or q quits.
+fn browse(nav: &mut Navigator, frecency_db: &mut Option<FrecencyDb>) -> Option<PathBuf> {
+    let mut type_ahead = String::new();
+    let mut type_ahead_time = Instant::now();
+    // vim style count prefix of the next movement, 0 if none is typed
+    let mut count: usize = 0;
 
-    let mut cur_position = CdlsCurPosition {
-        cur_dir: cur_path.clone(),
-        cur_item: cur_path.clone(),
-    };
-    
     loop {
-        let (dir_children, cursor) = main_screen_update(&mut cur_position, maxy, &cdls_cfg);
-        maxy = ncurses::getmaxy(ncurses::stdscr());
-        let ch = ncurses::getch();
-        log::debug!("press {}", ch);
+        let (dir_children, cursor) = main_screen_update(&mut nav.position, &nav.cfg);
+        let key = take_key(&mut nav.position, wait_key());
+        let ch = key.code();
+        log::debug!("press {:?}", key);
         log::debug!("cursor {}", cursor);
         log::debug!("dir_children len {}", dir_children.len());
-        match ch {
-            ncurses::KEY_UP => {
-                if cursor > 0 && dir_children.len() > 1  {
-                    cur_position.cur_item = dir_children[cursor - 1].clone();
+
+        // the next loop draws with the new size, keeping the pending type-ahead and count
+        if ch == backend::KEY_RESIZE {
+            continue;
+        }
+
+        // type-ahead: while typing within the timeout, action keys are typed too
+        let typing = !type_ahead.is_empty() && type_ahead_time.elapsed() < TYPE_AHEAD_TIMEOUT;
+        match key {
+            WideKey::Char(c) if !c.is_control() && (typing || !ACTION_KEYS.contains(&c)) => {
+                if !typing {
+                    type_ahead.clear();
                 }
+                type_ahead.push(c);
+                type_ahead_time = Instant::now();
+
+                // the count is dropped, "5" then "a" then down moves one row
+                count = 0;
+                nav.apply_listed(Action::TypeAhead(type_ahead.clone()), &dir_children, cursor);
+                continue;
             },
-            ncurses::KEY_DOWN => {
-                if dir_children.len() >= 1 && cursor < dir_children.len() - 1 {
-                    cur_position.cur_item = dir_children[cursor + 1].clone();
+            _ => {
+                type_ahead.clear();
+            }
+        }
+
+        let prev_count = count;
+        let repeat = prev_count.max(1) as isize;
+        count = 0;
+
+        let page = page_rows() as isize;
+        let action = match ch {
+            backend::KEY_UP | 107 /* k */ => Some(Action::Move(-repeat)),
+            backend::KEY_DOWN | 106 /* j */ => Some(Action::Move(repeat)),
+            backend::KEY_PPAGE | 2 /* ctrl-b */ => Some(Action::Move(-repeat * page)),
+            backend::KEY_NPAGE | 6 /* ctrl-f */ => Some(Action::Move(repeat * page)),
+            21 /* ctrl-u */ => Some(Action::Move(-repeat * (page / 2).max(1))),
+            4 /* ctrl-d */ => Some(Action::Move(repeat * (page / 2).max(1))),
+            backend::KEY_HOME => Some(Action::GotoLine(1)),
+            backend::KEY_END => Some(Action::Last),
+            103 /* g */ => Some(Action::GotoLine(prev_count.max(1))),
+            71 /* G */ => {
+                if prev_count > 0 {
+                    Some(Action::GotoLine(prev_count))
+                } else {
+                    Some(Action::Last)
                 }
             },
-            ncurses::KEY_LEFT => {
-                cur_position.cur_dir.pop();
-                cur_position.cur_item = cur_position.cur_dir.clone();
+            backend::KEY_LEFT => Some(Action::Parent),
+            backend::KEY_RIGHT => Some(Action::Open),
+            27 /* esc */ => Some(Action::ClearSearch),
+            _ => None,
+        };
+        if let Some(action) = action {
+            nav.apply_listed(action, &dir_children, cursor);
+            continue;
+        }
+
+        match ch {
+            48..=57 => { /* 0-9, count prefix */
+                count = (prev_count * 10 + (ch - 48) as usize).min(MAX_COUNT);
             },
-            ncurses::KEY_RIGHT => {
-                let child = &dir_children[cursor];
-                if child.is_dir() {
-                    cur_position.cur_dir.push(child.file_name().expect(""));
-                    cur_position.cur_item = cur_position.cur_dir.clone();
+            58 => { /* : */
+                if let Some(line) = line_number_prompt(&mut nav.position, &nav.cfg) {
+                    nav.apply_listed(Action::GotoLine(line), &dir_children, cursor);
                 }
             },
-            10 | ncurses::KEY_ENTER => { // enter
-                let mut child =  dir_children[cursor].clone();
-                if !child.is_dir() {
-                    child.pop();
+            10 | backend::KEY_ENTER => { // enter
+                let child = match nav.apply_listed(Action::Accept, &dir_children, cursor) {
+                    Some(child) => child,
+                    None => continue,
+                };
+
+                match set_current_dir(&child) {
+                    Ok(()) => {
+                        record_visit(frecency_db, &child);
+                        return Some(child);
+                    },
+                    Err(e) => {
+                        nav.position.status = Some(CdlsError::EnterDir(child, e).to_string());
+                    }
                 }
-                
-                set_current_dir(&child).unwrap(); // todo: handle error
-                break;
             },
             113 => { /* q */
                 log::warn!("q pressed, exit");
-                break;
+                return None;
             },
             99 => { /* c */
-                column_cfg(maxy, &mut cdls_cfg);
+                column_cfg(&mut nav.cfg);
             }
             102 => { /* f */
-                search_mode(&mut cur_position, maxy, &mut cdls_cfg);
+                let filter = nav.cfg.search_mode && nav.cfg.search_filter;
+                search_mode(&mut nav.position, &mut nav.cfg, filter);
+            }
+            47 => { /* / */
+                search_mode(&mut nav.position, &mut nav.cfg, true);
+            }
+            70 => { /* F */
+                recursive_search_mode(&mut nav.position, &mut nav.cfg);
             }
             104 => { /* h */
-                help_screen(maxy);
-                ncurses::getch(); /* press any key to exit help screen */
+                /* the arrows scroll the help screen, other keys exit it, a resize draws it again */
+                let mut offset = 0;
+                loop {
+                    offset = help_screen(offset);
+                    let page = page_rows().max(1);
+                    match wait_key().code() {
+                        backend::KEY_RESIZE => {},
+                        backend::KEY_DOWN => offset += 1,
+                        backend::KEY_UP => offset = offset.saturating_sub(1),
+                        backend::KEY_NPAGE => offset += page,
+                        backend::KEY_PPAGE => offset = offset.saturating_sub(page),
+                        _ => break,
+                    }
+                }
             },
             115 => { /* s */
-                sort_cfg(maxy, &mut cdls_cfg);
+                sort_cfg(&mut nav.cfg);
+            },
+            122 => { /* z */
+                if let Some(db) = frecency_db {
+                    jump_mode(&mut nav.position, db, &mut nav.cfg);
+                }
             },
             _ => {
                 continue;   
             }
         }
     }
+}
+
+fn screen_size() -> (i32, i32) {
+    return backend::size();
+}
+
+/// Whether the header and footer are drawn, they are hidden in a low terminal.
+fn show_bars() -> bool {
+    let (maxy, _) = screen_size();
+    return maxy >= BARS_MIN_HEIGHT;
+}
+
+/// Draw a message instead of the screen if the terminal is too small for it.
+///
+/// Return true if the message was drawn.
+fn draw_too_small() -> bool {
+    let (maxy, maxx) = screen_size();
+    if maxy >= MIN_HEIGHT && maxx >= MIN_WIDTH {
+        return false;
+    }
+
+    backend::clear();
+    backend::mv(0, 0);
+    let msg = if maxx > 18 { "Terminal too small" } else { "Too small" };
+    addstr_clipped(msg, screen_right());
+    backend::refresh();
+    return true;
+}
+
+/// Row of the first list item.
+fn list_top() -> i32 {
+    return show_bars() as i32;
+}
+
+/// Items shown in one page of the item list, without header and footer.
+fn page_rows() -> usize {
+    let (maxy, _) = screen_size();
+    let bars = if show_bars() { 2 } else { 0 };
+    return (maxy - bars).max(1) as usize;
+}
+
+/// Read an item number in the footer, None if cancelled by esc.
+fn line_number_prompt(cur_position: &mut CdlsCurPosition, cdls_cfg: &CdlsConfig) -> Option<usize> {
+    let mut line = String::new();
+    let mut line_cursor: usize = 0;
+
+    loop {
+        main_screen_update(cur_position, cdls_cfg);
+        if show_bars() {
+            let (maxy, _) = screen_size();
+            backend::mv(maxy - 1, 0);
+            backend::clrtoeol();
+            addstr_clipped(&format!("Go to line:{}", line), screen_right());
+        }
+        draw_input_cursor("Go to line:", &line, line_cursor);
+
+        let key = take_key(cur_position, wait_key());
+        log::debug!("press {:?}", key);
+
+        if let WideKey::Char(c) = key {
+            if !c.is_control() && !c.is_ascii_digit() {
+                continue;
+            }
+        }
+        if line_edit::edit_line(&mut line, &mut line_cursor, key) {
+            continue;
+        }
+
+        match key.code() {
+            10 | backend::KEY_ENTER => { // enter
+                return line.parse::<usize>().ok();
+            },
+            27 => { // esc
+                return None;
+            },
+            _ => {
+                continue;
+            }
+        }
+    }
+}
+
+/// The backend of the terminal, ncurses unless cdls is built with only the crossterm feature.
+#[cfg(feature = "ncurses")]
+fn new_backend() -> Box<dyn backend::Backend> {
+    return Box::new(backend::NcursesBackend::new());
+}
+
+#[cfg(all(feature = "crossterm", not(feature = "ncurses")))]
+fn new_backend() -> Box<dyn backend::Backend> {
+    return Box::new(backend::CrosstermBackend::new());
+}
+
+#[cfg(not(any(feature = "ncurses", feature = "crossterm")))]
+compile_error!("cdls needs the ncurses or crossterm feature for its terminal");
+
+/// The value of the option at `args[*i]`, which is skipped.
+fn option_value<'a>(args: &'a [String], i: &mut usize) -> &'a str {
+    *i += 1;
+    match args.get(*i) {
+        Some(value) => value,
+        None => {
+            eprintln!("{} needs a value", args[*i - 1]);
+            exit(1);
+        }
+    }
+}
+
+/// Rows and columns of `COLSxROWS`.
+fn parse_size(size: &str) -> Option<(i32, i32)> {
+    let (cols, rows) = size.split_once('x')?;
+    return Some((rows.parse().ok()?, cols.parse().ok()?));
+}
+
+/// replace current process context with bash
+fn exec_bash() -> ! {
+    let err = Command::new("bash").exec();
+    log::error!("Fail to exec bash. {}", err);
+    exit(1);
+}
+
+fn jump_cli(query_args: &[String]) {
+    let query = query_args.join(" ");
+    let mut db = open_frecency_db();
+    let mut cdls_cfg = CdlsConfig::default();
+    config::load_config_file(&mut cdls_cfg);
+
+    let target = match &db {
+        Some(db) => db.query(&query, cdls_cfg.search_pinyin).first().map(|e| e.path.clone()),
+        None => None,
+    };
+
+    match target {
+        Some(dir) => {
+            if let Err(e) = set_current_dir(&dir) {
+                eprintln!("cdls: fail to enter {}. {}", dir.display(), e);
+                exit(1);
+            }
+            record_visit(&mut db, &dir);
+            exec_bash();
+        },
+        None => {
+            eprintln!("cdls: no directory matches \"{}\"", query);
+            exit(1);
+        }
+    }
+}
+
+fn import_cli(import_args: &[String]) {
+    let kind = match import_args.first().and_then(|name| ImportKind::from_name(name)) {
+        Some(kind) if import_args.len() <= 2 => kind,
+        _ => {
+            print_help();
+            exit(1);
+        }
+    };
+    let file = import_args.get(1).map(PathBuf::from);
+
+    let mut db = match open_frecency_db() {
+        Some(db) => db,
+        None => {
+            eprintln!("cdls: HOME is not set");
+            exit(1);
+        }
+    };
+
+    match db.import(kind, file.as_deref()) {
+        Ok(count) => {
+            if let Err(e) = db.save() {
+                eprintln!("cdls: fail to save frecency database. {}", e);
+                exit(1);
+            }
+            println!("Imported {} directories", count);
+        },
+        Err(e) => {
+            eprintln!("cdls: fail to import. {}", e);
+            exit(1);
+        }
+    }
+    exit(0);
+}
+
+/// Print the items of `dir` sorted as in the config, and quit.
+fn list_cli(dir: &Path, format: ListFormat) {
+    let mut cdls_cfg = CdlsConfig::default();
+    config::load_config_file(&mut cdls_cfg);
+
+    let (items, status) = match listing::list_dir(dir, &cdls_cfg) {
+        Ok(listing) => listing,
+        Err(e) => {
+            eprintln!("cdls: {}", e);
+            exit(1);
+        }
+    };
+
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    if let Err(e) = listing::write_listing(&mut out, &items, &cdls_cfg, format).and_then(|_| out.flush()) {
+        // the reader quit early, like `head`
+        if e.kind() != io::ErrorKind::BrokenPipe {
+            eprintln!("cdls: fail to print the list. {}", e);
+            exit(1);
+        }
+    }
+    if let Some(status) = status {
+        eprintln!("cdls: {}", status);
+        exit(1);
+    }
+    exit(0);
+}
+
+fn main() {
+    
+    let args: Vec<String> = env::args().collect();
+    if args.len() >= 2 && args[1] == "jump" {
+        jump_cli(&args[2..]);
+    }
+    if args.len() >= 2 && args[1] == "import" {
+        import_cli(&args[2..]);
+    }
+
+    let mut debug_mode = false;
+    let mut keys_script: Option<String> = None;
+    let mut dump = false;
+    let mut size = (REPLAY_ROWS, REPLAY_COLS);
+    let mut list_dir: Option<PathBuf> = None;
+    let mut list_format: Option<ListFormat> = None;
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--help" | "-h" => {
+                print_help();
+                exit(0);
+            },
+            "--debug" | "-d" => {
+                debug_mode = true;
+            },
+            "--keys" => {
+                keys_script = Some(option_value(&args, &mut i).to_string());
+            },
+            "--keys-file" => {
+                match script::read_keys_file(Path::new(option_value(&args, &mut i))) {
+                    Ok(content) => keys_script = Some(content),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        exit(1);
+                    }
+                }
+            },
+            "--dump" => {
+                dump = true;
+            },
+            "--size" => {
+                size = match parse_size(option_value(&args, &mut i)) {
+                    Some(size) => size,
+                    None => {
+                        eprintln!("--size takes COLSxROWS, like 80x24");
+                        exit(1);
+                    }
+                };
+            },
+            "--list" => {
+                // the directory is optional
+                let dir = args.get(i + 1).filter(|arg| !arg.starts_with('-'));
+                if dir.is_some() {
+                    i += 1;
+                }
+                list_dir = Some(PathBuf::from(dir.map_or(".", String::as_str)));
+            },
+            "--format" => {
+                list_format = match ListFormat::from_name(option_value(&args, &mut i)) {
+                    Some(format) => Some(format),
+                    None => {
+                        eprintln!("--format takes text, json, csv or ndjson");
+                        exit(1);
+                    }
+                };
+            },
+            _ => {
+                print_help();
+                exit(0);
+            }
+        }
+        i += 1;
+    }
 
-    ncurses::echo();
-    ncurses::keypad(ncurses::stdscr(), false);
-    ncurses::curs_set(ncurses::CURSOR_VISIBILITY::CURSOR_VISIBLE);
-    ncurses::endwin();
+    if debug_mode {
+        match fs::File::create(".cdls.log") {
+            Ok(fd) => {
+                WriteLogger::init(LevelFilter::Debug, Config::default(), fd).unwrap();
+            },
+            Err(io_error) => {
+                println!("Fail to create log file .cdls.log, {}", io_error);
+                exit(1);
+            },
+        };  
+    }
+
+    // in a pipeline the items are printed, there is no terminal to browse them in
+    let piped = !io::stdout().is_terminal() && keys_script.is_none() && !dump;
+    if list_dir.is_some() || list_format.is_some() || piped {
+        list_cli(list_dir.as_deref().unwrap_or(Path::new(".")), list_format.unwrap_or(ListFormat::Text));
+    }
+
+    let rst = env::current_dir();
+    let cur_path = match rst {
+        Ok(path) => path,
+        Err(e) => {
+            log::error!("Fail to open current directory. {}", e);
+            exit(1);
+        }
+    };
+
+    if let Err(e) = terminal::install_signal_handlers() {
+        log::warn!("Fail to install signal handlers. {}", e);
+    }
+
+    if keys_script.is_some() || dump {
+        let keys = match script::parse_keys(keys_script.as_deref().unwrap_or_default()) {
+            Ok(keys) => keys,
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        };
+        script::start(keys, size.0, size.1, dump);
+    } else {
+        backend::install(new_backend());
+    }
+    backend::start();
+    terminal::install_panic_hook();
+
+    let mut cdls_cfg = CdlsConfig::default();
+    // a replay draws the same whatever the config of the user
+    if !script::is_active() {
+        config::load_config_file(&mut cdls_cfg);
+    }
+    let mut nav = Navigator::new(cur_path, cdls_cfg);
+
+    // a replay goes the same whatever the user visited, and leaves the database as it is
+    let mut frecency_db = if script::is_active() { None } else { open_frecency_db() };
+    let accepted = browse(&mut nav, &mut frecency_db);
+
+    if script::is_active() {
+        script::finish(accepted.as_deref());
+    }
+    terminal::restore_terminal();
 
-    // replace current process context with bash
-    Command::new("bash").exec();
     // todo: bug: bash recusively call bash
     // fix: use "exec cdls" to start cdls
+    exec_bash();
+}
 
-    exit(0);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scroll_viewport_keeps_the_cursor_visible() {
+        // cursor at the first and the last item
+        assert_eq!(scroll_viewport(5, 0, 40, 10, 0, false), 0);
+        assert_eq!(scroll_viewport(0, 39, 40, 10, 0, false), 30);
+        // the offset stays while the cursor is within the rows
+        assert_eq!(scroll_viewport(12, 15, 40, 10, 0, false), 12);
+        // centered
+        assert_eq!(scroll_viewport(0, 20, 40, 10, 0, true), 15);
+        assert_eq!(scroll_viewport(0, 2, 40, 10, 0, true), 0);
+        assert_eq!(scroll_viewport(0, 39, 40, 10, 0, true), 30);
+    }
+
+    #[test]
+    fn scroll_viewport_short_lists() {
+        // fewer items than rows never scroll
+        assert_eq!(scroll_viewport(3, 4, 5, 10, 2, false), 0);
+        assert_eq!(scroll_viewport(3, 4, 5, 10, 2, true), 0);
+        assert_eq!(scroll_viewport(3, 4, 10, 10, 2, false), 0);
+        assert_eq!(scroll_viewport(0, 0, 5, 0, 2, false), 0);
+    }
+
+    #[test]
+    fn scroll_viewport_scrolloff() {
+        // scrolloff rows are kept above and below the cursor
+        assert_eq!(scroll_viewport(0, 8, 40, 10, 2, false), 1);
+        assert_eq!(scroll_viewport(10, 11, 40, 10, 2, false), 9);
+        // but not past the ends of the list
+        assert_eq!(scroll_viewport(5, 0, 40, 10, 2, false), 0);
+        assert_eq!(scroll_viewport(0, 39, 40, 10, 2, false), 30);
+        // a scrolloff over half the rows is clamped, the cursor stays near the middle
+        assert_eq!(scroll_viewport(0, 20, 40, 10, 100, false), 15);
+        assert_eq!(scroll_viewport(15, 21, 40, 10, 100, false), 16);
+        assert_eq!(scroll_viewport(0, 20, 40, 1, 100, false), 20);
+    }
 }
@@ -0,0 +1,720 @@
+use std::fs;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use chrono::offset::{Local, Utc};
+use chrono::{DateTime, NaiveDateTime};
+use rust_fuzzy_search::fuzzy_compare;
+
+use crate::config::{clear_search, new_matcher, CdlsConfig, SortBy};
+use crate::error::CdlsError;
+use crate::quote::{self, QuotingStyle};
+use crate::search::{self, Matcher, SearchAlgo};
+
+/// Where the browser is: the current directory and the item under the cursor.
+pub struct CdlsCurPosition {
+    pub cur_dir: PathBuf,
+    pub cur_item: PathBuf,
+    // first item shown in the item list
+    pub scroll_offset: usize,
+    // last directory listed without error, kept when another one can't be listed
+    pub listed_dir: PathBuf,
+    // shown in the footer until the next key
+    pub status: Option<String>,
+}
+
+impl CdlsCurPosition {
+    /// At `dir`, with the cursor on its first item.
+    pub fn new(dir: PathBuf) -> CdlsCurPosition {
+        return CdlsCurPosition {
+            cur_dir: dir.clone(),
+            cur_item: dir.clone(),
+            scroll_offset: 0,
+            listed_dir: dir,
+            status: None,
+        };
+    }
+}
+
+pub trait PathBufExt {
+    fn file_size(&self) -> u64;
+    fn file_modified_time(&self) -> DateTime<Utc>;
+    fn file_type(&self) -> &str;
+    fn fuzzy_search_score(&self, search_str: &str, pinyin: bool) -> f32;
+    fn search_score(&self, matcher: &Matcher) -> Option<f32>;
+    fn display_name(&self, quoting: QuotingStyle) -> String;
+}
+
+impl PathBufExt for PathBuf {
+    fn file_size(&self) -> u64 {
+        match fs::symlink_metadata(self) {
+            Ok(md) => {
+                return md.len();
+            }
+            Err(_) => {
+                return 0;
+            }
+        }
+    }
+
+    fn file_modified_time(&self) -> DateTime<Utc> {
+        match fs::symlink_metadata(self) {
+            Ok(md) => {
+                match md.modified() {
+                    Ok(time) => {
+                        return time.into();
+                    }
+                    Err(_) => {
+                        return DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(0, 0), Utc);
+                    }
+                }
+            }
+            Err(_) => {
+                return DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(0, 0), Utc);
+            }
+        }
+    }
+
+    fn file_type(&self) -> &str {
+        let metadata;
+
+        match fs::symlink_metadata(self) {
+            Ok(md) => {
+                metadata = md;
+            }
+            Err(_) => {
+                return "NO-PERMISSION"
+            }
+        }    
+    
+        let file_type = metadata.file_type();
+        if file_type.is_dir() {
+            return "DIR";
+        }  else if file_type.is_symlink() {
+            return "SYMLINK";
+        } else if file_type.is_socket() || file_type.is_fifo() {
+            return "FD";
+        } else if file_type.is_block_device() || file_type.is_char_device() {
+            return "DEV";
+        } else if file_type.is_file() {
+            return "FILE";
+        } else {
+            return "UNKNOWN";
+        }
+    }
+
+    fn fuzzy_search_score(&self, search_str: &str, pinyin: bool) -> f32 {
+        let file_name = self.display_name(QuotingStyle::Escape);
+        if !pinyin {
+            return fuzzy_compare(search_str, &file_name);
+        }
+        // names with Han characters also match by their pinyin spellings
+        return search::romanize(&file_name).iter()
+            .map(|(spelling, _)| fuzzy_compare(search_str, spelling))
+            .fold(fuzzy_compare(search_str, &file_name), f32::max);
+    }
+
+    /// Score of the escaped name, which is the name itself unless it has characters which can't be
+    /// drawn, whatever quoting style it is drawn in.
+    fn search_score(&self, matcher: &Matcher) -> Option<f32> {
+        return matcher.score(&self.display_name(QuotingStyle::Escape));
+    }
+
+    /// The file name as it is drawn and searched, quoted so it is safe to draw.
+    fn display_name(&self, quoting: QuotingStyle) -> String {
+        match self.file_name() {
+            Some(name) => quote::quote_name(name, quoting),
+            None => String::new(),
+        }
+    }
+}
+
+/// Return the items to display, and the number of items in the directory before search filtering.
+///
+/// Entries which can't be read are skipped and reported in the status line.
+pub fn get_current_dir_element(cur_position: &mut CdlsCurPosition, cdls_cfg: &CdlsConfig)
+        -> Result<(Vec<PathBuf>, usize), CdlsError> {
+    let mut children = Vec::new();
+
+    let read_dir_iter = match fs::read_dir(&cur_position.cur_dir) {
+        Ok(iter) => iter,
+        Err(e) => return Err(CdlsError::ListDir(cur_position.cur_dir.clone(), e)),
+    };
+
+    let mut entry_errors = 0;
+    let mut first_error = None;
+    for f in read_dir_iter {
+        match f {
+            Ok(file) => {
+                children.push(file.path());
+            },
+            Err(e) => {
+                log::warn!("error: {}", e);
+                entry_errors += 1;
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    if let Some(e) = first_error {
+        cur_position.status = Some(CdlsError::ListEntries(cur_position.cur_dir.clone(), entry_errors, e).to_string());
+    }
+
+    let total = children.len();
+
+    match cdls_cfg.sortby {
+        SortBy::Filename => children.sort(),
+        SortBy::Size => children.sort_by_key(|a| a.file_size()),
+        SortBy::MTime => children.sort_by_key(|a| a.file_modified_time()),
+        SortBy::ItemType => children.sort_by(|a, b| a.file_type().cmp(b.file_type())),
+        //_ => {}, 
+    }
+
+    if cdls_cfg.search_mode {
+        let matcher = new_matcher(cdls_cfg, &cdls_cfg.search_string);
+        let mut scored: Vec<(PathBuf, Option<f32>)> = children.into_iter()
+            .map(|child| {
+                let score = child.search_score(&matcher);
+                (child, score)
+            })
+            .collect();
+
+        if cdls_cfg.search_filter {
+            // the threshold only makes sense for the 0.0 to 1.0 trigram score
+            scored.retain(|(_, score)| match score {
+                Some(score) => matcher.algo != SearchAlgo::Trigram || *score >= cdls_cfg.search_threshold,
+                None => false,
+            });
+        }
+
+        // best match first and non-matches last, ties keep the sort order
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        children = scored.into_iter().map(|(child, _)| child).collect();
+    }
+
+    if !children.is_empty() && cur_position.cur_dir == cur_position.cur_item {
+        // cur_item not set. set it to the first item
+        cur_position.cur_item = children[0].clone();
+        log::warn!("set current postion: {}", cur_position.cur_item.display());
+    }
+
+    return Ok((children, total));
+}
+
+/// List the current directory. If it can't be listed, go back to the directory listed
+/// before, with the cursor on the one which failed, and tell why in the status line.
+pub fn list_current_dir(cur_position: &mut CdlsCurPosition, cdls_cfg: &CdlsConfig) -> (Vec<PathBuf>, usize) {
+    if !cur_position.cur_dir.is_dir() {
+        // deleted while browsing, go up to the nearest directory which still exists
+        let deleted_dir = cur_position.cur_dir.clone();
+        while !cur_position.cur_dir.is_dir() && cur_position.cur_dir.pop() {}
+        cur_position.cur_item = cur_position.cur_dir.clone();
+        cur_position.listed_dir = cur_position.cur_dir.clone();
+        cur_position.status = Some(CdlsError::Deleted(deleted_dir, cur_position.cur_dir.clone()).to_string());
+    }
+
+    match get_current_dir_element(cur_position, cdls_cfg) {
+        Ok(listing) => {
+            cur_position.listed_dir = cur_position.cur_dir.clone();
+            return listing;
+        },
+        Err(e) => {
+            log::warn!("{}", e);
+            cur_position.status = Some(e.to_string());
+        }
+    }
+
+    if cur_position.listed_dir == cur_position.cur_dir {
+        return (Vec::new(), 0);
+    }
+
+    let failed_dir = std::mem::replace(&mut cur_position.cur_dir, cur_position.listed_dir.clone());
+    cur_position.cur_item = if failed_dir.parent() == Some(cur_position.cur_dir.as_path()) {
+        failed_dir
+    } else {
+        cur_position.cur_dir.clone()
+    };
+
+    match get_current_dir_element(cur_position, cdls_cfg) {
+        Ok(listing) => listing,
+        Err(e) => {
+            log::warn!("{}", e);
+            cur_position.status = Some(e.to_string());
+            (Vec::new(), 0)
+        }
+    }
+}
+
+pub fn get_file_metadata_element(path: &PathBuf) -> (String, String, String) {
+
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(md) => md,
+        Err(_) => {
+            return (String::from("UNKNOWN"), String::from("UNKNOWN"), String::from("UNKNOWN"));
+        }
+    };
+
+    let permissions = metadata.permissions();
+    let mode = permissions.mode();
+    let size = metadata.len();
+
+    let mut permission_str = String::from("rwxrwxrwx");
+    
+    for i in 0..9 {
+        if mode & (1 << (8 - i)) == 0 {
+            permission_str.replace_range(i..i+1, "-");
+        }
+    }
+    
+    let size_str = size.to_string(); 
+
+    let modified_time_str = match metadata.modified() {
+        Ok(time) => {
+            let datetime: DateTime<Local> = time.into();
+            datetime.format("%Y-%m-%d %H:%M:%S").to_string()    
+        },
+        Err(_) => String::from("UNKNOWN")
+    };
+
+    return (permission_str, size_str, modified_time_str);
+}
+
+/// The next item whose name starts with `prefix` ignoring case, searching from the cursor on.
+/// A single character prefix starts after the cursor, so typing it again cycles through the items.
+pub fn type_ahead_match(dir_children: &[PathBuf], cursor: usize, prefix: &str) -> Option<PathBuf> {
+    let prefix = prefix.to_lowercase();
+    let start = if prefix.chars().count() == 1 { cursor + 1 } else { cursor };
+
+    for i in 0..dir_children.len() {
+        let child = &dir_children[(start + i) % dir_children.len()];
+        // escaped, so the typed letters match the start of the name itself and not a shell quote
+        let name = child.display_name(QuotingStyle::Escape).to_lowercase();
+        if name.starts_with(&prefix) {
+            return Some(child.clone());
+        }
+    }
+
+    return None;
+}
+
+/// The item `delta` rows away from the cursor, clamped to the list.
+pub fn move_cursor(dir_children: &[PathBuf], cursor: usize, delta: isize) -> Option<PathBuf> {
+    if dir_children.is_empty() {
+        return None;
+    }
+    let target = (cursor as isize).saturating_add(delta).clamp(0, dir_children.len() as isize - 1);
+    return Some(dir_children[target as usize].clone());
+}
+
+/// Item number `line`, counted from 1 as shown by the position of the list.
+pub fn goto_line(dir_children: &[PathBuf], line: usize) -> Option<PathBuf> {
+    if dir_children.is_empty() {
+        return None;
+    }
+    let idx = line.clamp(1, dir_children.len()) - 1;
+    return Some(dir_children[idx].clone());
+}
+
+/// A key action of the main screen, apart from the keys that read input or open another screen.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Action {
+    /// Move the cursor by some rows, negative is up.
+    Move(isize),
+    /// Go to an item number, counted from 1.
+    GotoLine(usize),
+    /// Go to the last item.
+    Last,
+    /// Go to the next item whose name starts with the typed prefix.
+    TypeAhead(String),
+    /// Go to the parent directory.
+    Parent,
+    /// Go into the selected directory.
+    Open,
+    /// Search the current directory, hiding the items which don't match if `filter` is set.
+    Search { query: String, filter: bool },
+    /// Leave search mode.
+    ClearSearch,
+    /// Sort the items by another key.
+    Sort(SortBy),
+    /// Quit with the selected directory, or the directory of the selected file.
+    Accept,
+}
+
+/// An item of the listing, with the columns as they are drawn.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ItemView {
+    pub path: PathBuf,
+    pub name: String,
+    pub is_dir: bool,
+    pub file_type: String,
+    pub permission: String,
+    pub size: String,
+    pub mtime: String,
+    // the modification time in full, for the listings which print it with its time zone
+    pub modified: Option<SystemTime>,
+}
+
+impl ItemView {
+    /// The columns of `path`, with its name quoted in `quoting`.
+    pub fn new(path: PathBuf, quoting: QuotingStyle) -> ItemView {
+        let (permission, size, mtime) = get_file_metadata_element(&path);
+        return ItemView {
+            name: path.display_name(quoting),
+            is_dir: path.is_dir(),
+            file_type: path.file_type().to_string(),
+            permission,
+            size,
+            mtime,
+            modified: fs::symlink_metadata(&path).and_then(|md| md.modified()).ok(),
+            path,
+        };
+    }
+}
+
+/// What a front end draws: the current directory, its items and the cursor.
+#[derive(Debug, PartialEq, Clone)]
+pub struct View {
+    pub dir: PathBuf,
+    pub items: Vec<ItemView>,
+    // None if there are no items
+    pub cursor: Option<usize>,
+    // items in the directory before search filtering
+    pub total: usize,
+    pub search: Option<String>,
+    pub status: Option<String>,
+}
+
+/// The browser without a screen: it takes actions and lists the directory they lead to.
+///
+/// The directory is listed again for each action and view, so changes made meanwhile show up,
+/// unless the caller passes the listing it holds to `apply_listed`.
+pub struct Navigator {
+    pub cfg: CdlsConfig,
+    pub position: CdlsCurPosition,
+}
+
+impl Navigator {
+    pub fn new(dir: PathBuf, cfg: CdlsConfig) -> Navigator {
+        return Navigator {
+            cfg,
+            position: CdlsCurPosition::new(dir),
+        };
+    }
+
+    /// The items of the current directory, and the index of the selected one.
+    pub fn list(&mut self) -> (Vec<PathBuf>, usize) {
+        let (dir_children, _) = list_current_dir(&mut self.position, &self.cfg);
+        let cursor = dir_children.iter().position(|x| *x == self.position.cur_item).unwrap_or_default();
+        return (dir_children, cursor);
+    }
+
+    /// Apply `action` to the position. The status of the previous action is cleared.
+    ///
+    /// Return the directory to quit to after `Action::Accept`, the caller makes it current.
+    pub fn apply(&mut self, action: Action) -> Option<PathBuf> {
+        let (dir_children, cursor) = self.list();
+        return self.apply_listed(action, &dir_children, cursor);
+    }
+
+    /// Like `apply`, on the items and cursor the caller listed with the current position and
+    /// config, so the directory isn't listed again.
+    pub fn apply_listed(&mut self, action: Action, dir_children: &[PathBuf], cursor: usize) -> Option<PathBuf> {
+        self.position.status = None;
+
+        let moved = match action {
+            Action::Move(delta) => move_cursor(dir_children, cursor, delta),
+            Action::GotoLine(line) => goto_line(dir_children, line),
+            Action::Last => goto_line(dir_children, dir_children.len()),
+            Action::TypeAhead(prefix) => type_ahead_match(dir_children, cursor, &prefix),
+            Action::Parent => {
+                clear_search(&mut self.cfg);
+                self.position.cur_dir.pop();
+                self.position.cur_item = self.position.cur_dir.clone();
+                None
+            },
+            Action::Open => {
+                if let Some(child) = dir_children.get(cursor).filter(|child| child.is_dir()) {
+                    // an unreadable directory is not entered, the reason goes to the status line
+                    match fs::read_dir(child) {
+                        Ok(_) => {
+                            clear_search(&mut self.cfg);
+                            self.position.cur_dir = child.clone();
+                            self.position.cur_item = child.clone();
+                        },
+                        Err(e) => {
+                            self.position.status = Some(CdlsError::ListDir(child.clone(), e).to_string());
+                        }
+                    }
+                }
+                None
+            },
+            Action::Search { query, filter } => {
+                self.cfg.search_mode = true;
+                self.cfg.search_filter = filter;
+                self.cfg.search_cursor = query.chars().count();
+                self.cfg.search_string = query;
+                // the cursor goes to the best match
+                self.position.cur_item = self.position.cur_dir.clone();
+                None
+            },
+            Action::ClearSearch => {
+                clear_search(&mut self.cfg);
+                None
+            },
+            Action::Sort(sortby) => {
+                self.cfg.sortby = sortby;
+                None
+            },
+            Action::Accept => {
+                // an empty directory is accepted itself
+                let mut child = match dir_children.get(cursor) {
+                    Some(child) => child.clone(),
+                    None => self.position.cur_dir.clone(),
+                };
+                if !child.is_dir() {
+                    child.pop();
+                }
+                return Some(child);
+            },
+        };
+
+        if let Some(item) = moved {
+            self.position.cur_item = item;
+        }
+        return None;
+    }
+
+    /// List the current directory for drawing.
+    pub fn view(&mut self) -> View {
+        let (dir_children, total) = list_current_dir(&mut self.position, &self.cfg);
+        let cursor = dir_children.iter().position(|x| *x == self.position.cur_item).unwrap_or_default();
+        let items = dir_children.into_iter()
+            .map(|path| ItemView::new(path, self.cfg.quoting_style))
+            .collect::<Vec<ItemView>>();
+
+        return View {
+            dir: self.position.cur_dir.clone(),
+            cursor: if items.is_empty() { None } else { Some(cursor) },
+            items,
+            total,
+            search: if self.cfg.search_mode { Some(self.cfg.search_string.clone()) } else { None },
+            status: self.position.status.clone(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    use crate::search::SearchAlgo;
+
+    /// A directory with the files `b.txt` (3 bytes), `a.txt` (10 bytes), `c.md` (1 byte)
+    /// and the directories `dir` (with `inner.txt`) and `empty`.
+    fn sample_dir() -> TempDir {
+        let tmp = tempfile::tempdir().unwrap();
+        for (name, size) in [("b.txt", 3), ("a.txt", 10), ("c.md", 1)] {
+            File::create(tmp.path().join(name)).unwrap().write_all(&vec![b'x'; size]).unwrap();
+        }
+        fs::create_dir(tmp.path().join("dir")).unwrap();
+        File::create(tmp.path().join("dir/inner.txt")).unwrap();
+        fs::create_dir(tmp.path().join("empty")).unwrap();
+        return tmp;
+    }
+
+    fn navigator(tmp: &TempDir) -> Navigator {
+        return Navigator::new(tmp.path().to_path_buf(), CdlsConfig::default());
+    }
+
+    fn names(view: &View) -> Vec<&str> {
+        return view.items.iter().map(|item| item.name.as_str()).collect();
+    }
+
+    fn selected(nav: &mut Navigator) -> String {
+        let view = nav.view();
+        return view.items[view.cursor.unwrap()].name.clone();
+    }
+
+    #[test]
+    fn lists_sorted_by_name_with_cursor_on_first_item() {
+        let tmp = sample_dir();
+        let mut nav = navigator(&tmp);
+        let view = nav.view();
+        assert_eq!(names(&view), ["a.txt", "b.txt", "c.md", "dir", "empty"]);
+        assert_eq!(view.cursor, Some(0));
+        assert_eq!(view.total, 5);
+        assert_eq!(view.dir, tmp.path());
+        assert!(view.items[3].is_dir);
+        assert_eq!(view.items[3].file_type, "DIR");
+        assert_eq!(view.items[1].size, "3");
+        assert_eq!(view.status, None);
+    }
+
+    #[test]
+    fn moves_are_clamped_to_the_list() {
+        let tmp = sample_dir();
+        let mut nav = navigator(&tmp);
+        nav.apply(Action::Move(2));
+        assert_eq!(selected(&mut nav), "c.md");
+        nav.apply(Action::Move(100));
+        assert_eq!(selected(&mut nav), "empty");
+        nav.apply(Action::Move(-100));
+        assert_eq!(selected(&mut nav), "a.txt");
+        nav.apply(Action::GotoLine(2));
+        assert_eq!(selected(&mut nav), "b.txt");
+        nav.apply(Action::GotoLine(0));
+        assert_eq!(selected(&mut nav), "a.txt");
+        nav.apply(Action::Last);
+        assert_eq!(selected(&mut nav), "empty");
+    }
+
+    #[test]
+    fn applies_actions_to_the_listing_of_the_caller() {
+        let tmp = sample_dir();
+        let mut nav = navigator(&tmp);
+        let (dir_children, cursor) = nav.list();
+        nav.apply_listed(Action::Move(2), &dir_children, cursor);
+        assert_eq!(selected(&mut nav), "c.md");
+        let accepted = nav.apply_listed(Action::Accept, &dir_children, 3);
+        assert_eq!(accepted, Some(tmp.path().join("dir")));
+    }
+
+    #[test]
+    fn type_ahead_cycles_through_matching_names() {
+        let tmp = sample_dir();
+        let mut nav = navigator(&tmp);
+        nav.apply(Action::TypeAhead(String::from("d")));
+        assert_eq!(selected(&mut nav), "dir");
+        nav.apply(Action::TypeAhead(String::from("E")));
+        assert_eq!(selected(&mut nav), "empty");
+        nav.apply(Action::TypeAhead(String::from("x")));
+        assert_eq!(selected(&mut nav), "empty");
+    }
+
+    #[test]
+    fn opens_directories_and_goes_back_up() {
+        let tmp = sample_dir();
+        let mut nav = navigator(&tmp);
+        nav.apply(Action::Open);
+        assert_eq!(nav.view().dir, tmp.path(), "a file is not opened");
+
+        nav.apply(Action::GotoLine(4));
+        nav.apply(Action::Open);
+        let view = nav.view();
+        assert_eq!(view.dir, tmp.path().join("dir"));
+        assert_eq!(names(&view), ["inner.txt"]);
+
+        nav.apply(Action::Parent);
+        assert_eq!(nav.view().dir, tmp.path());
+    }
+
+    #[test]
+    fn empty_directory_has_no_cursor() {
+        let tmp = sample_dir();
+        let mut nav = navigator(&tmp);
+        nav.apply(Action::Last);
+        nav.apply(Action::Open);
+        let view = nav.view();
+        assert!(view.items.is_empty());
+        assert_eq!(view.cursor, None);
+        nav.apply(Action::Move(1));
+        assert_eq!(nav.apply(Action::Accept), Some(tmp.path().join("empty")));
+    }
+
+    #[test]
+    fn accepts_the_directory_of_the_selected_item() {
+        let tmp = sample_dir();
+        let mut nav = navigator(&tmp);
+        assert_eq!(nav.apply(Action::Accept), Some(tmp.path().to_path_buf()));
+        nav.apply(Action::GotoLine(4));
+        assert_eq!(nav.apply(Action::Accept), Some(tmp.path().join("dir")));
+        assert_eq!(nav.apply(Action::Move(1)), None);
+    }
+
+    #[test]
+    fn sorts_by_size() {
+        let tmp = sample_dir();
+        let mut nav = navigator(&tmp);
+        nav.apply(Action::Sort(SortBy::Size));
+        let view = nav.view();
+        let files: Vec<&str> = names(&view).into_iter().filter(|name| name.contains('.')).collect();
+        assert_eq!(files, ["c.md", "b.txt", "a.txt"]);
+    }
+
+    #[test]
+    fn search_filters_and_ranks_items() {
+        let tmp = sample_dir();
+        let mut nav = navigator(&tmp);
+        nav.cfg.search_algo = SearchAlgo::Substring;
+        nav.apply(Action::Search { query: String::from("txt"), filter: true });
+        let view = nav.view();
+        assert_eq!(names(&view), ["a.txt", "b.txt"]);
+        assert_eq!(view.total, 5);
+        assert_eq!(view.search.as_deref(), Some("txt"));
+
+        nav.apply(Action::Search { query: String::from("md"), filter: false });
+        let view = nav.view();
+        assert_eq!(names(&view)[0], "c.md");
+        assert_eq!(view.items.len(), 5);
+
+        nav.apply(Action::ClearSearch);
+        let view = nav.view();
+        assert_eq!(names(&view), ["a.txt", "b.txt", "c.md", "dir", "empty"]);
+        assert_eq!(view.search, None);
+    }
+
+    #[test]
+    fn trigram_search_hides_items_below_the_threshold() {
+        let tmp = sample_dir();
+        fs::write(tmp.path().join("txt"), "").unwrap();
+        let mut nav = navigator(&tmp);
+        nav.cfg.search_algo = SearchAlgo::Trigram;
+        nav.apply(Action::Search { query: String::from("txt"), filter: true });
+        assert_eq!(names(&nav.view()), ["txt", "a.txt", "b.txt"]);
+
+        // only the exact name scores 1.0
+        nav.cfg.search_threshold = 1.0;
+        assert_eq!(names(&nav.view()), ["txt"]);
+    }
+
+    #[test]
+    fn search_matches_names_quoted_for_the_shell() {
+        let tmp = sample_dir();
+        fs::write(tmp.path().join("my notes"), "").unwrap();
+        let mut nav = navigator(&tmp);
+        nav.cfg.quoting_style = QuotingStyle::Shell;
+        nav.cfg.search_algo = SearchAlgo::Prefix;
+        nav.apply(Action::Search { query: String::from("my"), filter: true });
+        assert_eq!(names(&nav.view()), ["'my notes'"]);
+    }
+
+    #[test]
+    fn deleted_directory_goes_up_with_a_status() {
+        let tmp = sample_dir();
+        let mut nav = navigator(&tmp);
+        nav.apply(Action::GotoLine(4));
+        nav.apply(Action::Open);
+        fs::remove_dir_all(tmp.path().join("dir")).unwrap();
+
+        let view = nav.view();
+        assert_eq!(view.dir, tmp.path());
+        assert!(view.status.unwrap().contains("no longer exists"));
+
+        nav.apply(Action::Move(1));
+        assert_eq!(nav.view().status, None, "the status is cleared by the next action");
+    }
+
+    #[test]
+    fn names_are_quoted() {
+        let tmp = tempfile::tempdir().unwrap();
+        File::create(tmp.path().join("new\nline")).unwrap();
+        let mut nav = navigator(&tmp);
+        assert_eq!(names(&nav.view()), ["new\\nline"]);
+        nav.cfg.quoting_style = QuotingStyle::Shell;
+        assert_eq!(names(&nav.view()), ["'new'$'\\n''line'"]);
+    }
+}
@@ -0,0 +1,226 @@
+use std::ffi::OsStr;
+use std::fmt::Write;
+use std::os::unix::ffi::OsStrExt;
+
+/// How a file name is drawn, named after the `ls --quoting-style` it follows.
+///
+/// Control characters never reach the terminal as they are, so a name can't move the
+/// cursor or inject escape sequences.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum QuotingStyle {
+    /// Backslash escapes, like `new\nline` and `caf\351`.
+    Escape,
+    /// Quoted to be pasted in a shell, like `'new'$'\n''line'`.
+    Shell,
+    /// As it is, with a `?` for each control character and byte which is not UTF-8.
+    Literal,
+}
+
+impl QuotingStyle {
+    pub fn from_name(name: &str) -> Option<QuotingStyle> {
+        match name {
+            "escape" => Some(QuotingStyle::Escape),
+            "shell" => Some(QuotingStyle::Shell),
+            "literal" => Some(QuotingStyle::Literal),
+            _ => None,
+        }
+    }
+}
+
+/// A piece of a file name: a character, or a byte which is not UTF-8.
+enum Unit {
+    Char(char),
+    Byte(u8),
+}
+
+fn units(name: &OsStr) -> Vec<Unit> {
+    let mut units = Vec::new();
+    for chunk in name.as_bytes().utf8_chunks() {
+        units.extend(chunk.valid().chars().map(Unit::Char));
+        units.extend(chunk.invalid().iter().map(|b| Unit::Byte(*b)));
+    }
+    return units;
+}
+
+/// A quoted name, with the index of the unit each of its chars is drawn for. The quotes which
+/// open and close a run of the shell style belong to no unit.
+#[derive(Default)]
+struct Quoted {
+    text: String,
+    origin: Vec<Option<usize>>,
+}
+
+impl Quoted {
+    fn push_str(&mut self, piece: &str, unit: Option<usize>) {
+        for c in piece.chars() {
+            self.text.push(c);
+            self.origin.push(unit);
+        }
+    }
+}
+
+fn push_octal(out: &mut String, bytes: &[u8]) {
+    for b in bytes {
+        let _ = write!(out, "\\{:03o}", b);
+    }
+}
+
+/// A control character as a C escape.
+fn push_escaped_control(out: &mut String, c: char) {
+    match c {
+        '\n' => out.push_str("\\n"),
+        '\t' => out.push_str("\\t"),
+        '\r' => out.push_str("\\r"),
+        '\x07' => out.push_str("\\a"),
+        '\x08' => out.push_str("\\b"),
+        '\x0b' => out.push_str("\\v"),
+        '\x0c' => out.push_str("\\f"),
+        c => {
+            let mut buf = [0; 4];
+            push_octal(out, c.encode_utf8(&mut buf).as_bytes());
+        },
+    }
+}
+
+fn escape(units: &[Unit]) -> Quoted {
+    let mut out = Quoted::default();
+    for (i, unit) in units.iter().enumerate() {
+        let mut piece = String::new();
+        match unit {
+            Unit::Char('\\') => piece.push_str("\\\\"),
+            Unit::Char(c) if c.is_control() => push_escaped_control(&mut piece, *c),
+            Unit::Char(c) => piece.push(*c),
+            Unit::Byte(b) => push_octal(&mut piece, &[*b]),
+        }
+        out.push_str(&piece, Some(i));
+    }
+    return out;
+}
+
+/// Characters a shell takes literally outside of quotes.
+fn is_shell_safe(c: char) -> bool {
+    return c.is_alphanumeric() || "._-+,/:@%^=".contains(c);
+}
+
+fn shell_quote(units: &[Unit]) -> Quoted {
+    let plain = units.iter().all(|unit| match unit {
+        Unit::Char(c) => is_shell_safe(*c),
+        Unit::Byte(_) => false,
+    });
+    if plain && !units.is_empty() {
+        return literal(units);
+    }
+
+    // printable runs go in single quotes, the rest in $'...' with C escapes
+    let mut out = Quoted::default();
+    let mut in_quote = false;
+    for (i, unit) in units.iter().enumerate() {
+        match unit {
+            Unit::Char(c) if !c.is_control() => {
+                if !in_quote {
+                    out.push_str("'", None);
+                    in_quote = true;
+                }
+                if *c == '\'' {
+                    out.push_str("'\\''", Some(i));
+                } else {
+                    out.push_str(c.encode_utf8(&mut [0; 4]), Some(i));
+                }
+            },
+            _ => {
+                if in_quote {
+                    out.push_str("'", None);
+                    in_quote = false;
+                }
+                let mut piece = String::from("$'");
+                match unit {
+                    Unit::Char(c) => push_escaped_control(&mut piece, *c),
+                    Unit::Byte(b) => push_octal(&mut piece, &[*b]),
+                }
+                piece.push('\'');
+                out.push_str(&piece, Some(i));
+            },
+        }
+    }
+    if in_quote {
+        out.push_str("'", None);
+    }
+    return out;
+}
+
+fn literal(units: &[Unit]) -> Quoted {
+    let mut out = Quoted::default();
+    for (i, unit) in units.iter().enumerate() {
+        let c = match unit {
+            Unit::Char(c) if !c.is_control() => *c,
+            _ => '?',
+        };
+        out.push_str(c.encode_utf8(&mut [0; 4]), Some(i));
+    }
+    return out;
+}
+
+fn quote_units(units: &[Unit], style: QuotingStyle) -> Quoted {
+    match style {
+        QuotingStyle::Escape => escape(units),
+        QuotingStyle::Shell => shell_quote(units),
+        QuotingStyle::Literal => literal(units),
+    }
+}
+
+/// A file name as `ls --quoting-style` shows it, so any name can be drawn, searched and
+/// compared as a string.
+pub fn quote_name(name: &OsStr, style: QuotingStyle) -> String {
+    return quote_units(&units(name), style).text;
+}
+
+/// Names are searched in the escape style, which leaves printable names as they are. Move the
+/// char `positions` matched in the escaped `name` onto the name drawn in `style`, so the chars
+/// drawn for the matched characters are highlighted.
+pub fn map_escape_positions(name: &OsStr, positions: &[usize], style: QuotingStyle) -> Vec<usize> {
+    let units = units(name);
+    let escaped = escape(&units);
+    let matched: Vec<usize> = positions.iter().filter_map(|p| escaped.origin.get(*p).copied().flatten()).collect();
+    let drawn = quote_units(&units, style);
+    return drawn.origin.iter().enumerate()
+        .filter(|(_, unit)| match unit {
+            Some(unit) => matched.contains(unit),
+            None => false,
+        })
+        .map(|(i, _)| i)
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::ffi::OsStrExt;
+
+    fn quote(name: &[u8], style: QuotingStyle) -> String {
+        return quote_name(OsStr::from_bytes(name), style);
+    }
+
+    #[test]
+    fn quotes_in_each_style() {
+        assert_eq!(quote(b"a.txt", QuotingStyle::Shell), "a.txt");
+        assert_eq!(quote(b"my dir", QuotingStyle::Shell), "'my dir'");
+        assert_eq!(quote(b"it's\n", QuotingStyle::Shell), "'it'\\''s'$'\\n'");
+        assert_eq!(quote(b"new\nline\\", QuotingStyle::Escape), "new\\nline\\\\");
+        assert_eq!(quote(b"caf\xe9", QuotingStyle::Escape), "caf\\351");
+        assert_eq!(quote(b"caf\xe9\t", QuotingStyle::Literal), "caf??");
+    }
+
+    #[test]
+    fn maps_positions_onto_the_drawn_name() {
+        let name = OsStr::from_bytes(b"my dir");
+        // the opening quote shifts the shell style by one
+        assert_eq!(map_escape_positions(name, &[0, 1], QuotingStyle::Shell), [1, 2]);
+        assert_eq!(map_escape_positions(name, &[0, 1], QuotingStyle::Escape), [0, 1]);
+
+        // an escape is highlighted whole
+        let name = OsStr::from_bytes(b"a\nb");
+        assert_eq!(map_escape_positions(name, &[1, 3], QuotingStyle::Escape), [1, 2, 3]);
+        assert_eq!(map_escape_positions(name, &[3], QuotingStyle::Shell), [9]);
+        assert_eq!(map_escape_positions(name, &[3], QuotingStyle::Literal), [2]);
+    }
+}
@@ -0,0 +1,254 @@
+use std::cmp::{Ordering as CmpOrdering, Reverse};
+use std::collections::{BinaryHeap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use glob::Pattern;
+
+use crate::quote::{self, QuotingStyle};
+use crate::search::Matcher;
+
+#[derive(Clone)]
+pub struct RecursiveOptions {
+    pub max_depth: usize,
+    // the best this many matches are kept
+    pub max_results: usize,
+    pub ignore: Vec<String>,
+}
+
+/// A search over the subtree of a directory, walked in a background thread.
+///
+/// Matches stream in through `poll`, and the best `max_results` are kept best match first in
+/// `results`. The whole subtree is walked, so a strong match deep down still makes it in.
+pub struct RecursiveSearch {
+    root: PathBuf,
+    max_results: usize,
+    rx: Receiver<(PathBuf, f32)>,
+    cancel: Arc<AtomicBool>,
+    pub results: Vec<(PathBuf, f32)>,
+    pub done: bool,
+}
+
+impl RecursiveSearch {
+    pub fn start(root: PathBuf, matcher: Matcher, opts: &RecursiveOptions) -> RecursiveSearch {
+        let (tx, rx) = channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let ignore: Vec<Pattern> = opts.ignore.iter()
+            .filter_map(|p| match Pattern::new(p) {
+                Ok(pattern) => Some(pattern),
+                Err(e) => {
+                    log::warn!("invalid ignore pattern {}: {}", p, e);
+                    None
+                }
+            })
+            .collect();
+
+        let walk_root = root.clone();
+        let walk_cancel = cancel.clone();
+        let walk_opts = opts.clone();
+        thread::spawn(move || {
+            walk(walk_root, &matcher, &walk_opts, &ignore, &walk_cancel, &tx);
+        });
+
+        return RecursiveSearch {
+            root,
+            max_results: opts.max_results,
+            rx,
+            cancel,
+            results: Vec::new(),
+            done: false,
+        };
+    }
+
+    /// Collect the matches found since the last poll, return whether there are new ones.
+    pub fn poll(&mut self) -> bool {
+        let mut updated = false;
+
+        loop {
+            match self.rx.try_recv() {
+                Ok(result) => {
+                    self.results.push(result);
+                    updated = true;
+                },
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if !self.done {
+                        self.done = true;
+                        updated = true;
+                    }
+                    break;
+                }
+            }
+        }
+
+        if updated {
+            let root = &self.root;
+            self.results.sort_by(|a, b| {
+                b.1.total_cmp(&a.1).then_with(|| {
+                    let a_rel = a.0.strip_prefix(root).unwrap_or(&a.0);
+                    let b_rel = b.0.strip_prefix(root).unwrap_or(&b.0);
+                    a_rel.as_os_str().len().cmp(&b_rel.as_os_str().len())
+                })
+            });
+            self.results.truncate(self.max_results);
+        }
+
+        return updated;
+    }
+}
+
+impl Drop for RecursiveSearch {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// A score ordered with `total_cmp`, for the heap of the best matches.
+#[derive(PartialEq)]
+struct Score(f32);
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Score) -> Option<CmpOrdering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Score) -> CmpOrdering {
+        return self.0.total_cmp(&other.0);
+    }
+}
+
+/// Breadth first, so shallow matches arrive first. A match is only sent if it is among the best
+/// `max_results` found so far, a later match of the same score doesn't replace an earlier one.
+fn walk(root: PathBuf, matcher: &Matcher, opts: &RecursiveOptions, ignore: &[Pattern], cancel: &AtomicBool,
+        tx: &Sender<(PathBuf, f32)>) {
+    let mut queue = VecDeque::new();
+    queue.push_back((root.clone(), 1));
+    // the scores of the best matches, worst on top
+    let mut best: BinaryHeap<Reverse<Score>> = BinaryHeap::new();
+
+    while let Some((dir, depth)) = queue.pop_front() {
+        let read_dir_iter = match fs::read_dir(&dir) {
+            Ok(iter) => iter,
+            Err(e) => {
+                log::debug!("skip {}: {}", dir.display(), e);
+                continue;
+            }
+        };
+
+        for entry in read_dir_iter.flatten() {
+            if cancel.load(Ordering::Relaxed) {
+                return;
+            }
+
+            let name = entry.file_name();
+            let name_str = quote::quote_name(&name, QuotingStyle::Escape);
+            if ignore.iter().any(|p| p.matches(&name_str)) {
+                continue;
+            }
+
+            let path = entry.path();
+            // matched escaped, like the names of the main screen
+            let rel_path = quote::quote_name(path.strip_prefix(&root).unwrap_or(&path).as_os_str(),
+                QuotingStyle::Escape);
+            if let Some(score) = matcher.score(&rel_path) {
+                let is_best = best.len() < opts.max_results
+                    || best.peek().is_some_and(|Reverse(worst)| score > worst.0);
+                if is_best {
+                    if best.len() >= opts.max_results {
+                        best.pop();
+                    }
+                    best.push(Reverse(Score(score)));
+                    if tx.send((path.clone(), score)).is_err() {
+                        return;
+                    }
+                }
+            }
+
+            // symlinks are not followed, they could loop
+            let is_dir = match entry.file_type() {
+                Ok(file_type) => file_type.is_dir(),
+                Err(_) => false,
+            };
+            if is_dir && depth < opts.max_depth {
+                queue.push_back((path, depth + 1));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchAlgo;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn touch(root: &std::path::Path, rel_path: &str) {
+        let path = root.join(rel_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+
+    fn options(max_depth: usize, max_results: usize, ignore: &[&str]) -> RecursiveOptions {
+        return RecursiveOptions {
+            max_depth,
+            max_results,
+            ignore: ignore.iter().map(|p| p.to_string()).collect(),
+        };
+    }
+
+    /// The relative paths of the results once the walk is done.
+    fn search(root: &TempDir, query: &str, opts: &RecursiveOptions) -> Vec<String> {
+        let matcher = Matcher::new(SearchAlgo::Substring, query, false, false);
+        let mut search = RecursiveSearch::start(root.path().to_path_buf(), matcher, opts);
+        while !search.done {
+            search.poll();
+            thread::sleep(Duration::from_millis(1));
+        }
+        return search.results.iter()
+            .map(|(path, _)| path.strip_prefix(root.path()).unwrap().to_string_lossy().to_string())
+            .collect();
+    }
+
+    #[test]
+    fn stops_at_the_depth_limit() {
+        let root = TempDir::new().unwrap();
+        touch(root.path(), "x1");
+        touch(root.path(), "a/x2");
+        touch(root.path(), "a/b/x3");
+        let mut found = search(&root, "x", &options(2, 100, &[]));
+        found.sort();
+        assert_eq!(found, ["a/x2", "x1"]);
+    }
+
+    #[test]
+    fn skips_ignored_names() {
+        let root = TempDir::new().unwrap();
+        touch(root.path(), "node_modules/x/index.js");
+        touch(root.path(), "src/x.rs");
+        let found = search(&root, "x", &options(8, 100, &["node_*"]));
+        assert_eq!(found, ["src/x.rs"]);
+    }
+
+    #[test]
+    fn keeps_the_best_matches_up_to_the_cap() {
+        let root = TempDir::new().unwrap();
+        for i in 0..5 {
+            touch(root.path(), &format!("weak_match_{}_needle", i));
+        }
+        // found last, breadth first, but the best match
+        touch(root.path(), "d/e/needle");
+        let found = search(&root, "needle", &options(8, 2, &[]));
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0], "d/e/needle");
+    }
+}
@@ -0,0 +1,354 @@
+//! Snapshots of the screens drawn on a screen in memory, in `src/snapshots`.
+//!
+//! Run with `UPDATE_SNAPSHOTS=1` to write the snapshots again after a change of the layout. Rows
+//! starting with `@` list the attributes of a screen, see `render_attrs`.
+
+use std::fs::{self, File};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+use std::time::{Duration, UNIX_EPOCH};
+
+use cdls::backend::{self, Attr, MemoryBackend};
+use cdls::search::SearchAlgo;
+
+use super::*;
+
+static UTC: Once = Once::new();
+
+/// Draw with `draw` on a screen of `rows` by `cols` cells, and return its text.
+fn render(rows: i32, cols: i32, draw: impl FnOnce()) -> String {
+    // the modification times are shown in the local time zone, set before any test draws as
+    // the tests run in parallel
+    UTC.call_once(|| env::set_var("TZ", "UTC"));
+    backend::install(Box::new(MemoryBackend::new(rows, cols, Vec::new())));
+    draw();
+    let mut screen = String::new();
+    for line in backend::lines() {
+        screen.push_str(&line);
+        screen.push('\n');
+    }
+    return screen;
+}
+
+fn attr_names(attr: Attr) -> String {
+    let names = [(backend::A_BOLD, "bold"), (backend::A_DIM, "dim"), (backend::A_UNDERLINE, "underline"),
+        (backend::A_REVERSE, "reverse"), (backend::A_HIGHLIGHT, "highlight"), (backend::A_WIN, "win")];
+    let set: Vec<&str> = names.iter().filter(|(a, _)| attr & a != 0).map(|(_, name)| *name).collect();
+    return set.join("+");
+}
+
+/// Like `render`, followed by the attributes of the screen: for each row with any, the column
+/// ranges of the cells with the same attributes, like `@2 0-5 highlight, 3-4 highlight+bold`.
+fn render_attrs(rows: i32, cols: i32, draw: impl FnOnce()) -> String {
+    let mut screen = render(rows, cols, draw);
+    let grid = backend::screen_grid().unwrap();
+    for (y, row) in grid.rows().iter().enumerate() {
+        let mut runs: Vec<(usize, usize, Attr)> = Vec::new();
+        for (x, cell) in row.iter().enumerate() {
+            match runs.last_mut() {
+                Some((_, end, attr)) if *end + 1 == x && *attr == cell.attr => *end = x,
+                _ if cell.attr != 0 => runs.push((x, x, cell.attr)),
+                _ => {},
+            }
+        }
+        if !runs.is_empty() {
+            let runs: Vec<String> = runs.iter()
+                .map(|(start, end, attr)| format!("{}-{} {}", start, end, attr_names(*attr)))
+                .collect();
+            screen.push_str(&format!("@{} {}\n", y, runs.join(", ")));
+        }
+    }
+    return screen;
+}
+
+/// Screens drawn one after another, each with a label.
+#[derive(Default)]
+struct Snapshot {
+    text: String,
+}
+
+impl Snapshot {
+    fn add(&mut self, label: &str, screen: String) {
+        self.text.push_str(&format!("--- {}\n", label));
+        self.text.push_str(&screen);
+    }
+
+    fn check(&self, name: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/snapshots").join(format!("{}.txt", name));
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, &self.text).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(&path)
+            .unwrap_or_else(|_| panic!("no snapshot {}, run with UPDATE_SNAPSHOTS=1 to write it", path.display()));
+        assert!(self.text == expected, "snapshot {} differs, run with UPDATE_SNAPSHOTS=1 if expected:\n{}",
+            name, self.text);
+    }
+}
+
+/// A file of `size` bytes with fixed permissions and modification time, so they draw the same
+/// on every machine.
+fn add_file(dir: &Path, name: &str, size: usize, mode: u32, days: u64) {
+    let path = dir.join(name);
+    fs::write(&path, vec![b'x'; size]).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+    let mtime = UNIX_EPOCH + Duration::from_secs(1_600_000_000 + days * 86_400);
+    File::options().write(true).open(&path).unwrap().set_modified(mtime).unwrap();
+}
+
+/// A fresh directory named after the test, relative to the package so the header is the same
+/// everywhere. Tests run from the package directory.
+fn fixture_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from("target/snapshot-fixtures").join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    return dir;
+}
+
+/// Files only: the size and time of a directory depend on the file system.
+fn files_fixture(name: &str) -> PathBuf {
+    let dir = fixture_dir(name);
+    add_file(&dir, "a.txt", 12, 0o644, 0);
+    add_file(&dir, "b_longer_name_for_truncation_checks.tar.gz", 3000, 0o644, 30);
+    add_file(&dir, "c.md", 0, 0o600, 10);
+    add_file(&dir, "run.sh", 120, 0o755, 20);
+    add_file(&dir, "数据报告.csv", 42, 0o644, 5);
+    return dir;
+}
+
+fn many_fixture(name: &str) -> PathBuf {
+    let dir = fixture_dir(name);
+    for i in 0..40 {
+        add_file(&dir, &format!("file_{:02}.txt", i), i, 0o644, i as u64);
+    }
+    return dir;
+}
+
+fn position(dir: &Path) -> CdlsCurPosition {
+    return CdlsCurPosition::new(dir.to_path_buf());
+}
+
+/// The config with only the given columns, and the search as it is drawn in the snapshots.
+fn columns(item_type: bool, permission: bool, size: bool, mtime: bool) -> CdlsConfig {
+    return CdlsConfig {
+        item_type,
+        permission,
+        size,
+        mtime,
+        search_algo: SearchAlgo::Substring,
+        ..CdlsConfig::default()
+    };
+}
+
+fn main_screen(rows: i32, cols: i32, cur_position: &mut CdlsCurPosition, cfg: &CdlsConfig) -> String {
+    return render(rows, cols, || {
+        main_screen_update(cur_position, cfg);
+    });
+}
+
+fn main_screen_attrs(rows: i32, cols: i32, cur_position: &mut CdlsCurPosition, cfg: &CdlsConfig) -> String {
+    return render_attrs(rows, cols, || {
+        main_screen_update(cur_position, cfg);
+    });
+}
+
+#[test]
+fn main_screen_column_combinations() {
+    let dir = files_fixture("columns");
+    let mut snapshot = Snapshot::default();
+    for bits in 0..16 {
+        let cfg = columns(bits & 1 != 0, bits & 2 != 0, bits & 4 != 0, bits & 8 != 0);
+        let label = format!("type {} permission {} size {} mtime {}", cfg.item_type, cfg.permission, cfg.size,
+            cfg.mtime);
+        snapshot.add(&label, main_screen(8, 100, &mut position(&dir), &cfg));
+    }
+    snapshot.check("main_screen_column_combinations");
+}
+
+#[test]
+fn main_screen_sort_modes() {
+    let dir = files_fixture("sort");
+    let mut snapshot = Snapshot::default();
+    for sortby in [SortBy::Filename, SortBy::Size, SortBy::MTime] {
+        let cfg = CdlsConfig { sortby, ..columns(true, true, true, true) };
+        snapshot.add(&sortby.to_string(), main_screen(8, 100, &mut position(&dir), &cfg));
+    }
+
+    // items of the same type stay in the order of the directory, so one item of each type
+    let dir = fixture_dir("sort_type");
+    fs::create_dir(dir.join("b_dir")).unwrap();
+    add_file(&dir, "c_file", 1, 0o644, 0);
+    std::os::unix::fs::symlink("c_file", dir.join("a_link")).unwrap();
+    let cfg = CdlsConfig { sortby: SortBy::ItemType, ..columns(true, false, false, false) };
+    snapshot.add(&SortBy::ItemType.to_string(), main_screen(8, 100, &mut position(&dir), &cfg));
+    snapshot.check("main_screen_sort_modes");
+}
+
+#[test]
+fn main_screen_search_states() {
+    let dir = files_fixture("search");
+    let mut snapshot = Snapshot::default();
+    let search = |editing: bool, filter: bool, query: &str| CdlsConfig {
+        search_mode: true,
+        search_editing: editing,
+        search_filter: filter,
+        search_string: String::from(query),
+        ..columns(true, false, true, false)
+    };
+
+    snapshot.add("editing, hiding non-matches",
+        main_screen_attrs(8, 100, &mut position(&dir), &search(true, true, "t")));
+    snapshot.add("editing, showing all",
+        main_screen_attrs(8, 100, &mut position(&dir), &search(true, false, "md")));
+    snapshot.add("accepted", main_screen_attrs(8, 100, &mut position(&dir), &search(false, true, "txt")));
+    snapshot.add("no matches", main_screen_attrs(8, 100, &mut position(&dir), &search(false, true, "zzz")));
+    // the full name of the selected item goes above the search footer
+    snapshot.add("accepted, selected name cut",
+        main_screen_attrs(8, 50, &mut position(&dir), &search(false, true, "tar")));
+    snapshot.add("pinyin", main_screen_attrs(8, 100, &mut position(&dir), &CdlsConfig {
+        search_pinyin: true,
+        ..search(false, true, "sjbg")
+    }));
+    snapshot.check("main_screen_search_states");
+}
+
+#[test]
+fn main_screen_scroll_positions() {
+    let dir = many_fixture("scroll");
+    let mut snapshot = Snapshot::default();
+    let cfg = columns(false, false, true, false);
+
+    // scrolling down and back up keeps 3 rows around the cursor
+    let mut cur_position = position(&dir);
+    for i in [0, 6, 7, 20, 39, 36, 35, 0] {
+        cur_position.cur_item = dir.join(format!("file_{:02}.txt", i));
+        snapshot.add(&format!("cursor on {}", i), main_screen(12, 60, &mut cur_position, &cfg));
+    }
+
+    let centered = CdlsConfig { center_cursor: true, ..columns(false, false, true, false) };
+    let mut cur_position = position(&dir);
+    for i in [3, 20, 38] {
+        cur_position.cur_item = dir.join(format!("file_{:02}.txt", i));
+        snapshot.add(&format!("centered, cursor on {}", i), main_screen(12, 60, &mut cur_position, &centered));
+    }
+    snapshot.check("main_screen_scroll_positions");
+}
+
+#[test]
+fn main_screen_terminal_sizes() {
+    let dir = files_fixture("sizes");
+    let mut snapshot = Snapshot::default();
+    let cfg = columns(true, true, true, true);
+    for (cols, rows) in [(120, 10), (80, 8), (60, 8), (40, 8), (30, 5), (25, 3), (19, 8), (40, 2)] {
+        let mut cur_position = position(&dir);
+        cur_position.cur_item = dir.join("b_longer_name_for_truncation_checks.tar.gz");
+        snapshot.add(&format!("{}x{}", cols, rows), main_screen(rows, cols, &mut cur_position, &cfg));
+    }
+
+    let end = CdlsConfig { name_ellipsis: Ellipsis::End, ..columns(true, true, true, true) };
+    snapshot.add("ellipsis at the end, 60x8", main_screen(8, 60, &mut position(&dir), &end));
+    snapshot.check("main_screen_terminal_sizes");
+}
+
+#[test]
+fn main_screen_directories_and_empty() {
+    let dir = fixture_dir("directories");
+    fs::create_dir(dir.join("docs")).unwrap();
+    fs::create_dir(dir.join("empty")).unwrap();
+    add_file(&dir, "notes.txt", 1, 0o644, 0);
+    add_file(&dir, "new\nline", 1, 0o644, 0);
+
+    let mut snapshot = Snapshot::default();
+    let cfg = columns(true, false, false, false);
+    snapshot.add("directories", main_screen(8, 60, &mut position(&dir), &cfg));
+    let shell = CdlsConfig { quoting_style: QuotingStyle::Shell, ..columns(true, false, false, false) };
+    snapshot.add("shell quoting", main_screen(8, 60, &mut position(&dir), &shell));
+    snapshot.add("empty", main_screen(8, 60, &mut position(&dir.join("empty")), &cfg));
+    snapshot.check("main_screen_directories_and_empty");
+}
+
+#[test]
+fn column_cfg_screen() {
+    let mut snapshot = Snapshot::default();
+    for selected in 0..4 {
+        let cfg = columns(selected != 0, selected != 1, true, selected == 3);
+        snapshot.add(&format!("selected {}", selected), render(10, 60, || column_cfg_screen_update(&cfg, selected)));
+    }
+    snapshot.add("too small", render(2, 60, || column_cfg_screen_update(&CdlsConfig::default(), 0)));
+    snapshot.check("column_cfg_screen");
+}
+
+#[test]
+fn sort_cfg_screen() {
+    let mut snapshot = Snapshot::default();
+    for selected in SortBy::iter() {
+        let cfg = CdlsConfig { sortby: SortBy::Size, ..CdlsConfig::default() };
+        snapshot.add(&format!("selected {}", selected), render(10, 60, || sort_cfg_screen_update(&cfg, &selected)));
+    }
+    snapshot.check("sort_cfg_screen");
+}
+
+#[test]
+fn help_screen_sizes() {
+    let mut snapshot = Snapshot::default();
+    for (cols, rows) in [(160, 60), (80, 24), (40, 5), (10, 3)] {
+        snapshot.add(&format!("{}x{}", cols, rows), render(rows, cols, || { help_screen(0); }));
+    }
+    // scrolled past the end, the last page is shown
+    snapshot.add("80x24, scrolled to the end", render(24, 80, || { help_screen(1000); }));
+    snapshot.check("help_screen_sizes");
+}
+
+#[test]
+fn jump_mode_clears_an_accepted_search() {
+    let dir = fixture_dir("jump");
+    let target = dir.join("target");
+    fs::create_dir(&target).unwrap();
+    add_file(&target, "notes.md", 1, 0o644, 0);
+    let mut db = FrecencyDb::load(&dir.join("frecency"));
+    db.add(&target);
+
+    let mut cfg = CdlsConfig {
+        search_mode: true,
+        search_filter: true,
+        search_string: String::from("txt"),
+        ..columns(false, false, false, false)
+    };
+    let mut cur_position = position(&dir);
+    backend::install(Box::new(MemoryBackend::new(8, 60, vec![WideKey::Code(backend::KEY_ENTER)])));
+    jump_mode(&mut cur_position, &db, &mut cfg);
+
+    assert_eq!(cur_position.cur_dir, target);
+    assert!(!cfg.search_mode);
+    assert!(main_screen(8, 60, &mut cur_position, &cfg).contains("notes.md"));
+}
+
+#[test]
+fn type_ahead_drops_a_pending_count() {
+    let dir = many_fixture("type_ahead_count");
+    add_file(&dir, "apple.txt", 1, 0o644, 0);
+    let keys = vec![WideKey::Char('5'), WideKey::Char('a'), WideKey::Code(backend::KEY_DOWN), WideKey::Char('q')];
+    backend::install(Box::new(MemoryBackend::new(8, 60, keys)));
+    let mut nav = Navigator::new(dir.clone(), columns(false, false, false, false));
+    assert_eq!(browse(&mut nav, &mut None), None);
+
+    // the type-ahead selects apple.txt, the down key moves one row and not five
+    assert_eq!(nav.position.cur_item, dir.join("file_00.txt"));
+}
+
+#[test]
+fn jump_screen() {
+    let entries = [
+        frecency::FrecencyEntry { path: PathBuf::from("/home/user/projects/cdls"), rank: 12.5, last_access: 0 },
+        frecency::FrecencyEntry { path: PathBuf::from("/srv/data/报告"), rank: 3.0, last_access: 0 },
+        frecency::FrecencyEntry { path: PathBuf::from("/tmp/new\nline"), rank: 1.0, last_access: 0 },
+    ];
+    let entries: Vec<&frecency::FrecencyEntry> = entries.iter().collect();
+
+    let mut snapshot = Snapshot::default();
+    snapshot.add("cursor on 1", render(8, 60, || jump_screen_update(&entries, 1, "pro", QuotingStyle::Escape)));
+    snapshot.add("no entries", render(8, 60, || jump_screen_update(&[], 0, "xyz", QuotingStyle::Escape)));
+    snapshot.check("jump_screen");
+}
@@ -0,0 +1,137 @@
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::process::exit;
+use std::sync::OnceLock;
+
+use cdls::backend::{self, MemoryBackend, WideKey};
+
+use crate::terminal;
+
+// set while keys are replayed, whether the screen is printed at the end
+static REPLAY_DUMP: OnceLock<bool> = OnceLock::new();
+
+/// Keys named in `<...>`, vim style, and the actions of the main screen named by their key.
+fn named_key(name: &str) -> Option<WideKey> {
+    let key = match name.to_ascii_lowercase().as_str() {
+        "cr" | "enter" | "return" | "accept" => WideKey::Char('\n'),
+        "esc" => WideKey::Char('\x1b'),
+        "tab" => WideKey::Char('\t'),
+        "space" => WideKey::Char(' '),
+        "lt" => WideKey::Char('<'),
+        "bs" | "backspace" => WideKey::Code(backend::KEY_BACKSPACE),
+        "del" | "delete" => WideKey::Code(backend::KEY_DC),
+        "up" => WideKey::Code(backend::KEY_UP),
+        "down" => WideKey::Code(backend::KEY_DOWN),
+        "left" | "parent" => WideKey::Code(backend::KEY_LEFT),
+        "right" | "open" => WideKey::Code(backend::KEY_RIGHT),
+        "home" => WideKey::Code(backend::KEY_HOME),
+        "end" => WideKey::Code(backend::KEY_END),
+        "pageup" => WideKey::Code(backend::KEY_PPAGE),
+        "pagedown" => WideKey::Code(backend::KEY_NPAGE),
+        "quit" => WideKey::Char('q'),
+        "search" => WideKey::Char('/'),
+        "recursive" => WideKey::Char('F'),
+        "columns" => WideKey::Char('c'),
+        "sort" => WideKey::Char('s'),
+        "jump" => WideKey::Char('z'),
+        "help" => WideKey::Char('h'),
+        _ => {
+            // <C-x>, a control character
+            let letter = name.strip_prefix("C-").or_else(|| name.strip_prefix("c-"))?;
+            let mut chars = letter.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_alphabetic() => WideKey::Char((c.to_ascii_lowercase() as u8 & 0x1f) as char),
+                _ => return None,
+            }
+        },
+    };
+    return Some(key);
+}
+
+/// Parse a key script: characters are typed as they are, `<name>` is a named key or action.
+/// `<` without a closing `>` is typed too.
+pub fn parse_keys(script: &str) -> Result<Vec<WideKey>, String> {
+    let mut keys = Vec::new();
+    let mut rest = script;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if let Some(end) = rest.find('>') {
+                let name = &rest[1..end];
+                if !name.is_empty() && !name.contains('<') && !name.contains(char::is_whitespace) {
+                    match named_key(name) {
+                        Some(key) => keys.push(key),
+                        None => return Err(format!("unknown key <{}>, type < as <lt>", name)),
+                    }
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+        }
+        keys.push(WideKey::Char(c));
+        rest = &rest[c.len_utf8()..];
+    }
+    return Ok(keys);
+}
+
+/// A key script file: line breaks are ignored, and lines starting with `#` are comments.
+pub fn read_keys_file(path: &Path) -> Result<String, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("Fail to read {}. {}", path.display(), e))?;
+    return Ok(content.lines().filter(|line| !line.starts_with('#')).collect());
+}
+
+/// Replay `keys` on a screen in memory of `rows` by `cols` cells instead of the terminal.
+pub fn start(keys: Vec<WideKey>, rows: i32, cols: i32, dump: bool) {
+    backend::install(Box::new(MemoryBackend::new(rows, cols, keys)));
+    let _ = REPLAY_DUMP.set(dump);
+}
+
+pub fn is_active() -> bool {
+    return REPLAY_DUMP.get().is_some();
+}
+
+/// End the replay: print the screen if asked, and the directory `Enter` went to if any.
+pub fn finish(dir: Option<&Path>) -> ! {
+    terminal::restore_terminal();
+    let mut out = io::stdout().lock();
+    let mut rst = Ok(());
+    if REPLAY_DUMP.get() == Some(&true) {
+        for line in backend::lines() {
+            rst = rst.and_then(|_| writeln!(out, "{}", line));
+        }
+    }
+    if let Some(dir) = dir {
+        rst = rst.and_then(|_| out.write_all(dir.as_os_str().as_bytes()))
+            .and_then(|_| out.write_all(b"\n"));
+    }
+    if let Err(e) = rst.and_then(|_| out.flush()) {
+        log::warn!("Fail to print the replay. {}", e);
+        exit(1);
+    }
+    exit(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_characters_and_named_keys() {
+        let keys = parse_keys("j<CR><c-w><Parent>é<lt>").unwrap();
+        assert_eq!(keys, [WideKey::Char('j'), WideKey::Char('\n'), WideKey::Char('\x17'),
+            WideKey::Code(backend::KEY_LEFT), WideKey::Char('é'), WideKey::Char('<')]);
+    }
+
+    #[test]
+    fn types_a_lone_angle_bracket() {
+        let keys = parse_keys("a<b c>").unwrap();
+        assert_eq!(keys.len(), 6);
+        assert_eq!(parse_keys("<").unwrap(), [WideKey::Char('<')]);
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        assert!(parse_keys("<Enterr>").is_err());
+        assert!(parse_keys("<C-1>").is_err());
+    }
+}
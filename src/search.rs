@@ -0,0 +1,540 @@
+use std::fmt;
+
+use glob::{MatchOptions, Pattern};
+use pinyin::ToPinyin;
+use regex::{Regex, RegexBuilder};
+use rust_fuzzy_search::fuzzy_compare;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+
+// scores of the subsequence matcher, following fzf
+static SCORE_MATCH: i32 = 16;
+static SCORE_GAP_START: i32 = -3;
+static SCORE_GAP_EXTENSION: i32 = -1;
+static BONUS_BOUNDARY: i32 = 8;
+static BONUS_CAMEL: i32 = 7;
+static BONUS_CONSECUTIVE: i32 = 4;
+static BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+
+#[derive(Debug, EnumIter, PartialEq, Eq, Copy, Clone)]
+pub enum SearchAlgo {
+    Trigram,
+    Fuzzy,
+    Substring,
+    Prefix,
+    Glob,
+    Regex,
+}
+
+impl fmt::Display for SearchAlgo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SearchAlgo::Trigram => write!(f, "Trigram"),
+            SearchAlgo::Fuzzy => write!(f, "Fuzzy"),
+            SearchAlgo::Substring => write!(f, "Substring"),
+            SearchAlgo::Prefix => write!(f, "Prefix"),
+            SearchAlgo::Glob => write!(f, "Glob"),
+            SearchAlgo::Regex => write!(f, "Regex"),
+        }
+    }
+}
+
+impl SearchAlgo {
+    pub fn from_name(name: &str) -> Option<SearchAlgo> {
+        return SearchAlgo::iter().find(|algo| algo.to_string().eq_ignore_ascii_case(name));
+    }
+
+    pub fn next(self) -> SearchAlgo {
+        return SearchAlgo::iter().cycle().skip_while(|algo| *algo != self).nth(1).unwrap();
+    }
+}
+
+/// Decompose `text` (NFD), so composed and decomposed forms of a character compare equal.
+/// Combining marks are dropped if `ignore_diacritics`, so `é` is `e`.
+///
+/// Return the result, and for each of its chars the char index in `text` it comes from.
+fn normalize(text: &str, ignore_diacritics: bool) -> (String, Vec<usize>) {
+    let mut normalized = String::with_capacity(text.len());
+    let mut origin = Vec::with_capacity(text.len());
+
+    for (i, c) in text.chars().enumerate() {
+        decompose_canonical(c, |d| {
+            if !(ignore_diacritics && is_combining_mark(d)) {
+                normalized.push(d);
+                origin.push(i);
+            }
+        });
+    }
+
+    return (normalized, origin);
+}
+
+/// Pinyin spellings of the Han characters in `text`: full (`报告.docx` is `baogao.docx`),
+/// and initials (`bg.docx`). Empty if `text` has no Han characters.
+///
+/// Each is returned with the char index in `text` each of its chars comes from.
+pub fn romanize(text: &str) -> Vec<(String, Vec<usize>)> {
+    if !text.chars().any(|c| c.to_pinyin().is_some()) {
+        return Vec::new();
+    }
+
+    let mut full = (String::new(), Vec::new());
+    let mut initials = (String::new(), Vec::new());
+
+    for (i, c) in text.chars().enumerate() {
+        match c.to_pinyin() {
+            Some(pinyin) => {
+                for r in pinyin.plain().chars() {
+                    full.0.push(r);
+                    full.1.push(i);
+                }
+                for r in pinyin.first_letter().chars() {
+                    initials.0.push(r);
+                    initials.1.push(i);
+                }
+            },
+            None => {
+                full.0.push(c);
+                full.1.push(i);
+                initials.0.push(c);
+                initials.1.push(i);
+            }
+        }
+    }
+
+    return vec![full, initials];
+}
+
+/// A search string compiled for one algorithm.
+///
+/// Smart case: the search is case sensitive only if the search string has an uppercase character.
+/// Names and the search string are compared in Unicode normalized form. With `pinyin`, names
+/// with Han characters also match by their pinyin spellings.
+pub struct Matcher {
+    pub algo: SearchAlgo,
+    pub ignore_diacritics: bool,
+    pub pinyin: bool,
+    query: String,
+    case_sensitive: bool,
+    regex: Option<Regex>,
+    glob: Option<Pattern>,
+}
+
+impl Matcher {
+    pub fn new(algo: SearchAlgo, query: &str, ignore_diacritics: bool, pinyin: bool) -> Matcher {
+        let case_sensitive = query.chars().any(char::is_uppercase);
+        let (query, _) = normalize(query, ignore_diacritics);
+        let query = query.as_str();
+
+        let regex = match algo {
+            SearchAlgo::Regex => RegexBuilder::new(query).case_insensitive(!case_sensitive).build().ok(),
+            _ => None,
+        };
+        let glob = match algo {
+            SearchAlgo::Glob => Pattern::new(query).ok(),
+            _ => None,
+        };
+
+        return Matcher {
+            algo,
+            ignore_diacritics,
+            pinyin,
+            query: query.to_string(),
+            case_sensitive,
+            regex,
+            glob,
+        };
+    }
+
+    /// False if the search string doesn't compile, like an unclosed regex group.
+    pub fn is_valid(&self) -> bool {
+        match self.algo {
+            SearchAlgo::Regex => self.regex.is_some(),
+            SearchAlgo::Glob => self.glob.is_some(),
+            _ => true,
+        }
+    }
+
+    /// Normalized `name`, and its pinyin spellings if enabled.
+    /// Each comes with the char index in `name` each of its chars comes from.
+    fn variants(&self, name: &str) -> Vec<(String, Vec<usize>)> {
+        let mut variants = vec![(name.to_string(), (0..name.chars().count()).collect::<Vec<usize>>())];
+        if self.pinyin {
+            variants.extend(romanize(name));
+        }
+
+        return variants.into_iter()
+            .map(|(text, origin)| {
+                let (normalized, normalized_origin) = normalize(&text, self.ignore_diacritics);
+                (normalized, normalized_origin.iter().map(|i| origin[*i]).collect())
+            })
+            .collect();
+    }
+
+    /// Score of `name`, higher is better. None if it doesn't match.
+    pub fn score(&self, name: &str) -> Option<f32> {
+        return self.variants(name).iter()
+            .filter_map(|(text, _)| self.score_normalized(text))
+            .max_by(|a, b| a.total_cmp(b));
+    }
+
+    /// Char indices of `name` to highlight as matched.
+    pub fn positions(&self, name: &str) -> Vec<usize> {
+        let variants = self.variants(name);
+        let best = variants.iter()
+            .filter_map(|variant| self.score_normalized(&variant.0).map(|score| (score, variant)))
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+
+        match best {
+            Some((_, (text, origin))) => {
+                let mut positions: Vec<usize> = self.positions_normalized(text).iter()
+                    .map(|pos| origin[*pos])
+                    .collect();
+                positions.dedup();
+                return positions;
+            },
+            None => return Vec::new(),
+        }
+    }
+
+    fn score_normalized(&self, name: &str) -> Option<f32> {
+        if self.query.is_empty() {
+            return Some(0.0);
+        }
+
+        match self.algo {
+            SearchAlgo::Trigram => {
+                if !contains_in_order(&self.query, name, self.case_sensitive) {
+                    return None;
+                }
+                if self.case_sensitive {
+                    return Some(fuzzy_compare(&self.query, name));
+                } else {
+                    return Some(fuzzy_compare(&self.query.to_lowercase(), &name.to_lowercase()));
+                }
+            },
+            SearchAlgo::Fuzzy => {
+                return fuzzy_match(&self.query, name, self.case_sensitive).map(|(score, _)| score as f32);
+            },
+            SearchAlgo::Substring => {
+                // earlier and in shorter names first
+                let start = find_substring(&self.query, name, self.case_sensitive)?;
+                return Some(-(start as f32) - name.chars().count() as f32 / 1000.0);
+            },
+            SearchAlgo::Prefix => {
+                if find_substring(&self.query, name, self.case_sensitive) == Some(0) {
+                    return Some(-(name.chars().count() as f32));
+                }
+                return None;
+            },
+            SearchAlgo::Glob => {
+                let options = MatchOptions {
+                    case_sensitive: self.case_sensitive,
+                    require_literal_separator: false,
+                    require_literal_leading_dot: false,
+                };
+                match &self.glob {
+                    Some(pattern) if pattern.matches_with(name, options) => return Some(0.0),
+                    _ => return None,
+                }
+            },
+            SearchAlgo::Regex => {
+                let m = self.regex.as_ref()?.find(name)?;
+                return Some(-(name[..m.start()].chars().count() as f32));
+            },
+        }
+    }
+
+    fn positions_normalized(&self, name: &str) -> Vec<usize> {
+        if self.query.is_empty() {
+            return Vec::new();
+        }
+
+        match self.algo {
+            SearchAlgo::Trigram => {
+                return match_positions(&self.query, name, self.case_sensitive);
+            },
+            SearchAlgo::Fuzzy => {
+                return fuzzy_match(&self.query, name, self.case_sensitive)
+                    .map(|(_, positions)| positions)
+                    .unwrap_or_default();
+            },
+            SearchAlgo::Substring | SearchAlgo::Prefix => {
+                match self.score_normalized(name) {
+                    Some(_) => {
+                        let start = find_substring(&self.query, name, self.case_sensitive).unwrap_or_default();
+                        return (start..start + self.query.chars().count()).collect();
+                    },
+                    None => return Vec::new(),
+                }
+            },
+            SearchAlgo::Glob => {
+                // only the literal characters can be located
+                if self.score_normalized(name).is_none() || self.query.contains('[') {
+                    return Vec::new();
+                }
+                let literal: String = self.query.chars().filter(|c| *c != '*' && *c != '?').collect();
+                return match_positions(&literal, name, self.case_sensitive);
+            },
+            SearchAlgo::Regex => {
+                match self.regex.as_ref().and_then(|regex| regex.find(name)) {
+                    Some(m) => {
+                        let start = name[..m.start()].chars().count();
+                        let len = m.as_str().chars().count();
+                        return (start..start + len).collect();
+                    },
+                    None => return Vec::new(),
+                }
+            },
+        }
+    }
+}
+
+fn char_eq(a: char, b: char, case_sensitive: bool) -> bool {
+    if case_sensitive {
+        return a == b;
+    }
+    return a == b || a.to_lowercase().eq(b.to_lowercase());
+}
+
+/// Whether the characters of `query` appear in `name` in the same order.
+pub fn contains_in_order(query: &str, name: &str, case_sensitive: bool) -> bool {
+    let mut name_chars = name.chars();
+
+    for q in query.chars() {
+        if !name_chars.any(|c| char_eq(c, q, case_sensitive)) {
+            return false;
+        }
+    }
+
+    return true;
+}
+
+/// Char index of the first occurrence of `query` in `name`.
+fn find_substring(query: &str, name: &str, case_sensitive: bool) -> Option<usize> {
+    let query_chars: Vec<char> = query.chars().collect();
+    let name_chars: Vec<char> = name.chars().collect();
+
+    if query_chars.len() > name_chars.len() {
+        return None;
+    }
+
+    return (0..=(name_chars.len() - query_chars.len())).find(|start| {
+        query_chars.iter().enumerate().all(|(i, q)| char_eq(name_chars[start + i], *q, case_sensitive))
+    });
+}
+
+/// Char indices of `name` matched by `query`.
+///
+/// A contiguous match is preferred, otherwise the first in-order match is used.
+/// Empty if the characters of `query` are not all found in order.
+pub fn match_positions(query: &str, name: &str, case_sensitive: bool) -> Vec<usize> {
+    let query_len = query.chars().count();
+    if query_len == 0 {
+        return Vec::new();
+    }
+
+    if let Some(start) = find_substring(query, name, case_sensitive) {
+        return (start..start + query_len).collect();
+    }
+
+    let mut positions = Vec::new();
+    let mut query_iter = query.chars().peekable();
+    for (i, c) in name.chars().enumerate() {
+        match query_iter.peek() {
+            Some(q) => {
+                if char_eq(c, *q, case_sensitive) {
+                    positions.push(i);
+                    query_iter.next();
+                }
+            },
+            None => break,
+        }
+    }
+
+    if query_iter.peek().is_some() {
+        return Vec::new();
+    }
+    return positions;
+}
+
+/// Bonus of matching `cur`, the char after `prev`. Word starts score higher.
+fn char_bonus(prev: Option<char>, cur: char) -> i32 {
+    match prev {
+        None => BONUS_BOUNDARY,
+        Some(p) => {
+            if !p.is_alphanumeric() && cur.is_alphanumeric() {
+                BONUS_BOUNDARY
+            } else if (p.is_lowercase() && cur.is_uppercase()) || (!p.is_numeric() && cur.is_numeric()) {
+                BONUS_CAMEL
+            } else {
+                0
+            }
+        }
+    }
+}
+
+/// fzf style subsequence match: the best scored alignment of `query` in `name`,
+/// rewarding word starts and consecutive characters, penalizing gaps.
+fn fuzzy_match(query: &str, name: &str, case_sensitive: bool) -> Option<(i32, Vec<usize>)> {
+    let query_chars: Vec<char> = query.chars().collect();
+    let name_chars: Vec<char> = name.chars().collect();
+
+    if !contains_in_order(query, name, case_sensitive) {
+        return None;
+    }
+
+    let bonus: Vec<i32> = (0..name_chars.len())
+        .map(|j| char_bonus(if j == 0 { None } else { Some(name_chars[j - 1]) }, name_chars[j]))
+        .collect();
+
+    // score[i][j]: best score with query char i matched at name char j, prev[i][j]: where query char i - 1 matched
+    let mut score = vec![vec![None; name_chars.len()]; query_chars.len()];
+    let mut prev = vec![vec![0; name_chars.len()]; query_chars.len()];
+
+    for (i, q) in query_chars.iter().enumerate() {
+        // best predecessor at least one char before, with the gap penalty up to j
+        let mut gap_best: Option<(i32, usize)> = None;
+
+        for j in i..name_chars.len() {
+            if i > 0 {
+                gap_best = gap_best.map(|(s, k)| (s + SCORE_GAP_EXTENSION, k));
+                if j >= 2 {
+                    if let Some(prev_score) = score[i - 1][j - 2] {
+                        let candidate = prev_score + SCORE_GAP_START;
+                        if gap_best.is_none_or(|(s, _)| candidate > s) {
+                            gap_best = Some((candidate, j - 2));
+                        }
+                    }
+                }
+            }
+
+            if !char_eq(name_chars[j], *q, case_sensitive) {
+                continue;
+            }
+
+            if i == 0 {
+                score[i][j] = Some(SCORE_MATCH + bonus[j] * BONUS_FIRST_CHAR_MULTIPLIER);
+                continue;
+            }
+
+            let mut best = gap_best.map(|(s, k)| (s + bonus[j], k));
+            if let Some(prev_score) = score[i - 1][j - 1] {
+                let candidate = prev_score + BONUS_CONSECUTIVE.max(bonus[j]);
+                if best.is_none_or(|(s, _)| candidate >= s) {
+                    best = Some((candidate, j - 1));
+                }
+            }
+
+            if let Some((best_score, k)) = best {
+                score[i][j] = Some(best_score + SCORE_MATCH);
+                prev[i][j] = k;
+            }
+        }
+    }
+
+    let last = query_chars.len() - 1;
+    let (mut j, best_score) = score[last].iter().enumerate()
+        .filter_map(|(j, s)| s.map(|s| (j, s)))
+        .max_by_key(|(j, s)| (*s, std::cmp::Reverse(*j)))?;
+
+    let mut positions = vec![0; query_chars.len()];
+    for i in (0..query_chars.len()).rev() {
+        positions[i] = j;
+        if i > 0 {
+            j = prev[i][j];
+        }
+    }
+
+    return Some((best_score, positions));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(algo: SearchAlgo, query: &str) -> Matcher {
+        return Matcher::new(algo, query, false, false);
+    }
+
+    #[test]
+    fn fuzzy_ranks_word_starts_first() {
+        let m = matcher(SearchAlgo::Fuzzy, "m");
+        assert!(m.score("a.rs").is_none());
+        assert!(m.score("main.rs").unwrap() > m.score("am.rs").unwrap());
+        // trigram similarity ranked the shorter name first
+        let trigram = matcher(SearchAlgo::Trigram, "m");
+        assert!(trigram.score("a.rs").is_none());
+
+        let m = matcher(SearchAlgo::Fuzzy, "mr");
+        assert!(m.score("main.rs").unwrap() > m.score("mirror.txt").unwrap());
+        assert!(m.score("rm").is_none());
+    }
+
+    #[test]
+    fn uppercase_makes_the_search_case_sensitive() {
+        for algo in SearchAlgo::iter() {
+            // a glob matches the whole name
+            let (lower, upper) = if algo == SearchAlgo::Glob { ("ma*", "Ma*") } else { ("ma", "Ma") };
+            let lower = matcher(algo, lower);
+            assert!(lower.score("main.rs").is_some() && lower.score("Main.rs").is_some(), "{}", algo);
+            let upper = matcher(algo, upper);
+            assert!(upper.score("main.rs").is_none() && upper.score("Main.rs").is_some(), "{}", algo);
+        }
+    }
+
+    #[test]
+    fn prefix_and_substring() {
+        let prefix = matcher(SearchAlgo::Prefix, "ma");
+        assert!(prefix.score("main.rs").is_some());
+        assert!(prefix.score("a_main.rs").is_none());
+
+        let substring = matcher(SearchAlgo::Substring, "ma");
+        assert!(substring.score("main.rs").unwrap() > substring.score("a_main.rs").unwrap());
+        assert!(substring.score("m_a.rs").is_none());
+    }
+
+    #[test]
+    fn glob_matches_the_whole_name() {
+        let glob = matcher(SearchAlgo::Glob, "*.rs");
+        assert!(glob.score("main.rs").is_some());
+        assert!(glob.score("main.rs.bak").is_none());
+        assert_eq!(matcher(SearchAlgo::Glob, "ma*").positions("main.rs"), [0, 1]);
+    }
+
+    #[test]
+    fn invalid_regex_matches_nothing() {
+        let regex = matcher(SearchAlgo::Regex, "(ma");
+        assert!(!regex.is_valid());
+        assert!(regex.score("main.rs").is_none());
+        assert!(regex.positions("main.rs").is_empty());
+
+        let regex = matcher(SearchAlgo::Regex, "a.n");
+        assert!(regex.is_valid());
+        assert_eq!(regex.positions("main.rs"), [1, 2, 3]);
+    }
+
+    #[test]
+    fn positions_for_highlighting() {
+        assert_eq!(matcher(SearchAlgo::Fuzzy, "mrs").positions("main.rs"), [0, 5, 6]);
+        assert_eq!(matcher(SearchAlgo::Substring, "in").positions("main.rs"), [2, 3]);
+        assert_eq!(matcher(SearchAlgo::Trigram, "mn").positions("main.rs"), [0, 3]);
+        assert!(matcher(SearchAlgo::Substring, "x").positions("main.rs").is_empty());
+        // positions are char indices of the name as given, also when it is decomposed for matching
+        assert_eq!(matcher(SearchAlgo::Substring, "ab").positions("é_ab"), [2, 3]);
+    }
+
+    #[test]
+    fn romanize_spells_han_characters() {
+        let spellings = romanize("报告.docx");
+        assert_eq!(spellings[0], (String::from("baogao.docx"), vec![0, 0, 0, 1, 1, 1, 2, 3, 4, 5, 6]));
+        assert_eq!(spellings[1], (String::from("bg.docx"), vec![0, 1, 2, 3, 4, 5, 6]));
+        assert!(romanize("report.docx").is_empty());
+
+        let bg = Matcher::new(SearchAlgo::Prefix, "bg", false, true);
+        assert!(bg.score("报告.docx").is_some());
+        assert_eq!(bg.positions("报告.docx"), [0, 1]);
+        assert!(matcher(SearchAlgo::Prefix, "bg").score("报告.docx").is_none());
+    }
+}
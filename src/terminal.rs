@@ -0,0 +1,73 @@
+use std::io;
+use std::panic;
+use std::process::exit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::thread;
+
+use cdls::backend;
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM, SIGTSTP};
+
+// the last signal received and not handled yet, 0 if none
+static PENDING_SIGNAL: LazyLock<Arc<AtomicUsize>> = LazyLock::new(|| Arc::new(AtomicUsize::new(0)));
+
+/// Give the terminal back, with echo and the cursor on.
+pub fn restore_terminal() {
+    backend::stop();
+}
+
+/// Catch the signals which end or suspend cdls, they are handled by `handle_signals`.
+///
+/// Called before the backend starts, ncurses then leaves these signals to us.
+pub fn install_signal_handlers() -> io::Result<()> {
+    for sig in [SIGINT, SIGTERM, SIGHUP, SIGTSTP] {
+        signal_hook::flag::register_usize(sig, PENDING_SIGNAL.clone(), sig as usize)?;
+    }
+    return Ok(());
+}
+
+/// Restore the terminal before the panic message is printed, so it is readable.
+///
+/// Only a panic of the thread calling this, which draws the screen, ends cdls. A panic of a
+/// background thread like the recursive walk is logged and leaves the screen as it is.
+pub fn install_panic_hook() {
+    let ui_thread = thread::current().id();
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if thread::current().id() != ui_thread {
+            log::error!("{}", info);
+            return;
+        }
+        restore_terminal();
+        log::error!("{}", info);
+        default_hook(info);
+    }));
+}
+
+fn suspend() {
+    backend::suspend();
+    // stops here until the shell continues cdls
+    if let Err(e) = signal_hook::low_level::emulate_default_handler(SIGTSTP) {
+        log::warn!("Fail to suspend. {}", e);
+    }
+    backend::resume();
+}
+
+/// Handle a signal received since the last call: suspend on SIGTSTP, restore the terminal
+/// and exit on the others.
+///
+/// Return true if the screen has to be drawn again.
+pub fn handle_signals() -> bool {
+    match PENDING_SIGNAL.swap(0, Ordering::SeqCst) {
+        0 => false,
+        sig if sig == SIGTSTP as usize => {
+            suspend();
+            true
+        },
+        sig => {
+            restore_terminal();
+            log::warn!("exit on signal {}", sig);
+            exit(128 + sig as i32);
+        }
+    }
+}
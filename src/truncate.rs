@@ -0,0 +1,161 @@
+use unicode_normalization::char::is_combining_mark;
+use unicode_width::UnicodeWidthStr;
+
+pub static ELLIPSIS: &str = "…";
+
+/// Where a name too long for its column is cut.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Ellipsis {
+    Middle,
+    End,
+}
+
+impl Ellipsis {
+    pub fn from_name(name: &str) -> Option<Ellipsis> {
+        match name {
+            "middle" => Some(Ellipsis::Middle),
+            "end" => Some(Ellipsis::End),
+            _ => None,
+        }
+    }
+}
+
+/// A character of a name with the combining marks following it, and its char index.
+/// The ellipsis has no index.
+pub type Cluster = (Option<usize>, String);
+
+fn clusters(name: &str) -> Vec<Cluster> {
+    let mut clusters: Vec<Cluster> = Vec::new();
+    for (i, c) in name.chars().enumerate() {
+        match clusters.last_mut() {
+            Some((_, cluster)) if is_combining_mark(c) => cluster.push(c),
+            _ => clusters.push((Some(i), c.to_string())),
+        }
+    }
+    return clusters;
+}
+
+/// Clusters taken from the start of `clusters` while they fit in `width` cells.
+fn take_width(clusters: &[Cluster], width: usize) -> usize {
+    let mut used = 0;
+    let mut count = 0;
+    for (_, cluster) in clusters {
+        used += cluster.width();
+        if used > width {
+            break;
+        }
+        count += 1;
+    }
+    return count;
+}
+
+fn total_width(clusters: &[Cluster]) -> usize {
+    return clusters.iter().map(|(_, cluster)| cluster.width()).sum();
+}
+
+/// Cut `name` to `width` cells with an ellipsis, keeping the extension if it fits.
+///
+/// Return the clusters to draw, and whether the name was cut.
+pub fn truncate_name(name: &str, width: usize, style: Ellipsis) -> (Vec<Cluster>, bool) {
+    let all = clusters(name);
+    if total_width(&all) <= width {
+        return (all, false);
+    }
+    if width == 0 {
+        return (Vec::new(), true);
+    }
+
+    // cells left for the name besides the ellipsis
+    let avail = width - 1;
+
+    // a leading dot is a hidden file, not an extension
+    let ext_start = match name.rfind('.') {
+        Some(idx) if idx > 0 => name[..idx].chars().count(),
+        _ => name.chars().count(),
+    };
+    let ext_len = all.iter().filter(|(i, _)| i.unwrap_or_default() >= ext_start).count();
+    let ext_width = total_width(&all[all.len() - ext_len..]);
+
+    let tail_budget = match style {
+        // the extension, only if some of the name before it fits too
+        Ellipsis::End => if ext_width < avail { ext_width } else { 0 },
+        Ellipsis::Middle => {
+            if ext_width < avail {
+                ext_width.max(avail / 2)
+            } else {
+                avail / 2
+            }
+        },
+    };
+
+    let head_len = take_width(&all, avail - tail_budget);
+    let head_width = total_width(&all[..head_len]);
+
+    // wide characters may leave cells of the head budget unused, give them to the tail
+    let tail_rev: Vec<Cluster> = all[head_len..].iter().rev().cloned().collect();
+    let tail_len = match style {
+        Ellipsis::End if tail_budget == 0 => 0,
+        Ellipsis::End => ext_len.min(take_width(&tail_rev, avail - head_width)),
+        Ellipsis::Middle => take_width(&tail_rev, avail - head_width),
+    };
+
+    let mut result: Vec<Cluster> = all[..head_len].to_vec();
+    result.push((None, ELLIPSIS.to_string()));
+    result.extend_from_slice(&all[all.len() - tail_len..]);
+    return (result, true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cut(name: &str, width: usize, style: Ellipsis) -> (String, bool) {
+        let (clusters, cut) = truncate_name(name, width, style);
+        return (clusters.into_iter().map(|(_, cluster)| cluster).collect(), cut);
+    }
+
+    #[test]
+    fn names_which_fit_are_kept() {
+        assert_eq!(cut("a.txt", 5, Ellipsis::End), (String::from("a.txt"), false));
+        assert_eq!(cut("数据.csv", 8, Ellipsis::Middle), (String::from("数据.csv"), false));
+    }
+
+    #[test]
+    fn cuts_in_the_middle_or_at_the_end() {
+        assert_eq!(cut("abcdefghij", 6, Ellipsis::End), (String::from("abcde…"), true));
+        assert_eq!(cut("abcdefghij", 6, Ellipsis::Middle), (String::from("abc…ij"), true));
+    }
+
+    #[test]
+    fn keeps_the_extension() {
+        assert_eq!(cut("longname.txt", 8, Ellipsis::End).0, "lon….txt");
+        assert_eq!(cut("longname.txt", 8, Ellipsis::Middle).0, "lon….txt");
+        // unless none of the name before it would fit
+        assert_eq!(cut("a.verylongextension", 6, Ellipsis::End).0, "a.ver…");
+        // a leading dot is not an extension
+        assert_eq!(cut(".bashrc_local", 6, Ellipsis::End).0, ".bash…");
+    }
+
+    #[test]
+    fn wide_characters_straddling_the_cut() {
+        // 数 fits the head, 据 would straddle it, so the name is a cell short of the width
+        assert_eq!(cut("数据报告.csv", 8, Ellipsis::End).0, "数….csv");
+        // the cell the head leaves unused goes to the tail
+        assert_eq!(cut("数据报告数据报告", 7, Ellipsis::Middle).0, "数…报告");
+        assert_eq!(cut("数据", 2, Ellipsis::End).0, "…");
+    }
+
+    #[test]
+    fn widths_smaller_than_the_ellipsis() {
+        assert_eq!(cut("abc", 1, Ellipsis::End), (String::from("…"), true));
+        assert_eq!(cut("abc", 1, Ellipsis::Middle), (String::from("…"), true));
+        assert_eq!(cut("abc", 0, Ellipsis::End), (String::new(), true));
+    }
+
+    #[test]
+    fn combining_marks_stay_with_their_character() {
+        let (clusters, _) = truncate_name("e\u{301}e\u{301}e\u{301}e\u{301}", 3, Ellipsis::End);
+        assert_eq!(clusters, [(Some(0), String::from("e\u{301}")), (Some(2), String::from("e\u{301}")),
+            (None, String::from(ELLIPSIS))]);
+    }
+}
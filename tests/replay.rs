@@ -0,0 +1,104 @@
+//! Replays of key scripts through the `cdls` binary.
+
+#![allow(clippy::needless_return)]
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use tempfile::TempDir;
+
+/// Run cdls in `dir` with `args`, its home, config and data directories in `home`.
+fn cdls(dir: &Path, home: &Path, args: &[&str]) -> Output {
+    return Command::new(env!("CARGO_BIN_EXE_cdls"))
+        .args(args)
+        .current_dir(dir)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_DATA_HOME", home.join("data"))
+        .output()
+        .unwrap();
+}
+
+fn sample_dir() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.txt"), "").unwrap();
+    fs::write(dir.path().join("b.txt"), "").unwrap();
+    fs::create_dir(dir.path().join("dir")).unwrap();
+    return dir;
+}
+
+#[test]
+fn dumps_the_screen_and_the_selected_directory() {
+    let dir = sample_dir();
+    let home = TempDir::new().unwrap();
+    let output = cdls(dir.path(), home.path(), &["--keys", "jj<CR>", "--dump", "--size", "60x8"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 9);
+    assert!(lines[0].starts_with("CDLS # "));
+    assert!(lines[1].ends_with("a.txt"));
+    assert!(lines[3].starts_with(">>>>") && lines[3].ends_with("dir"));
+    assert_eq!(lines[8], dir.path().join("dir").to_str().unwrap());
+}
+
+#[test]
+fn leaves_the_config_and_the_database_alone() {
+    let dir = sample_dir();
+    let home = TempDir::new().unwrap();
+    fs::create_dir_all(home.path().join("config/cdls")).unwrap();
+    fs::write(home.path().join("config/cdls/config"), "columns = size\nsort_by = size\n").unwrap();
+
+    let output = cdls(dir.path(), home.path(), &["--keys", "<Accept>", "--dump", "--size", "60x8"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    // the default columns and sort, the permission column only shows with the default config
+    assert!(stdout.lines().nth(1).unwrap().contains("rw"));
+    assert!(stdout.lines().nth(1).unwrap().ends_with("a.txt"));
+    assert!(!home.path().join("data/cdls").exists());
+}
+
+#[test]
+fn jump_mode_ignores_the_database() {
+    let dir = sample_dir();
+    let home = TempDir::new().unwrap();
+    let visited = dir.path().join("dir");
+    let db = format!("{}|10|{}\n", visited.display(), 1_600_000_000);
+    fs::create_dir_all(home.path().join("data/cdls")).unwrap();
+    fs::write(home.path().join("data/cdls/frecency"), &db).unwrap();
+
+    let output = cdls(dir.path(), home.path(), &["--keys", "z", "--dump", "--size", "80x8"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    // no database, so no jump mode listing the visited directory
+    assert!(!stdout.starts_with("CDLS # Jump"), "{}", stdout);
+    assert!(!stdout.contains(visited.to_str().unwrap()), "{}", stdout);
+    assert_eq!(fs::read_to_string(home.path().join("data/cdls/frecency")).unwrap(), db);
+}
+
+#[test]
+fn rejects_unknown_key_names() {
+    let dir = sample_dir();
+    let home = TempDir::new().unwrap();
+    let output = cdls(dir.path(), home.path(), &["--keys", "<Nope>", "--dump"]);
+    assert!(!output.status.success());
+    assert!(!output.stderr.is_empty());
+}
+
+#[test]
+fn recursive_search_clears_an_accepted_search() {
+    let dir = sample_dir();
+    fs::write(dir.path().join("dir/needle"), "").unwrap();
+    let home = TempDir::new().unwrap();
+    let output = cdls(dir.path(), home.path(), &["--keys", "/txt<CR>Fneedle<CR>", "--dump", "--size", "60x8"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines[0].contains("/dir "), "{}", stdout);
+    // the match is shown, not hidden by the "txt" search
+    assert!(lines[1].starts_with(">>>>") && lines[1].ends_with("needle"), "{}", stdout);
+    assert!(!lines[7].starts_with("Search:"), "{}", stdout);
+}